@@ -0,0 +1,96 @@
+// Save a baseline before a change with `cargo bench --bench datapoints -- --save-baseline before`,
+// then compare with `cargo bench --bench datapoints -- --baseline before` after it.
+use chrono::{DateTime, TimeDelta, Utc};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use make_polar_rs::datapoints::{Data, DataPoint};
+use make_polar_rs::graph::{calculate_bin_values, GraphOptions};
+use std::io::{BufRead, Cursor};
+
+fn with_checksum(body: &str) -> String {
+    let checksum = body.bytes().fold(0u8, |a, b| a ^ b);
+    format!("${body}*{checksum:02X}")
+}
+
+// One ZDA, VHW and MWV sentence per second, which is enough for a complete datapoint each.
+fn synthetic_log(seconds: usize) -> String {
+    let start: DateTime<Utc> = "2024-06-05T00:00:00Z".parse().unwrap();
+    let mut log = String::new();
+    for second in 0..seconds {
+        let time = start + TimeDelta::seconds(second as i64);
+        let boatspeed = 4. + (second % 30) as f32 / 10.;
+        let windspeed = 10. + (second % 50) as f32 / 10.;
+        let windangle = 30. + (second % 120) as f32;
+        for body in [
+            format!("GPZDA,{},05,06,2024,,", time.format("%H%M%S.00")),
+            format!("VWVHW,,T,,M,{boatspeed:.1},N,{:.1},K,", boatspeed * 1.852),
+            format!("WIMWV,{windangle:.1},T,{windspeed:.1},N,A"),
+        ] {
+            log.push_str(&with_checksum(&body));
+            log.push('\n');
+        }
+    }
+    log
+}
+
+fn synthetic_data(points: usize) -> Data {
+    let start: DateTime<Utc> = "2024-06-05T00:00:00Z".parse().unwrap();
+    Data::from_datapoints(
+        (0..points)
+            .map(|i| DataPoint {
+                timestamp: start + TimeDelta::seconds(i as i64),
+                boatspeed: Some(4. + (i % 30) as f32 / 10.),
+                windspeed: Some(10. + (i % 50) as f32 / 10.),
+                winddirection: Some(30. + (i % 120) as f32),
+                ..DataPoint::new()
+            })
+            .collect(),
+    )
+}
+
+fn load_reader(c: &mut Criterion) {
+    let mut group = c.benchmark_group("load_reader");
+    for seconds in [1_000, 10_000] {
+        let log = synthetic_log(seconds);
+        group.bench_with_input(BenchmarkId::from_parameter(seconds), &log, |b, log| {
+            b.iter(|| {
+                let reader: Box<dyn BufRead> = Box::new(Cursor::new(log.clone().into_bytes()));
+                let mut data = Data::new();
+                data.load_reader(reader).unwrap();
+                black_box(data)
+            })
+        });
+    }
+    group.finish();
+}
+
+fn graph(c: &mut Criterion) {
+    let mut group = c.benchmark_group("graph");
+    group.sample_size(10);
+    for points in [1_000, 10_000, 100_000] {
+        let data = synthetic_data(points);
+        let start = data.data.first().unwrap().timestamp;
+        let end = data.data.last().unwrap().timestamp;
+        for width in [500, 1000, 2000] {
+            group.bench_with_input(
+                BenchmarkId::new(format!("{points} points"), width),
+                &width,
+                |b, &width| {
+                    b.iter(|| {
+                        black_box(data.graph(width, 400, start, end, &GraphOptions::default()))
+                    })
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+fn bin_values(c: &mut Criterion) {
+    let values: Vec<f32> = (0..1_000).map(|i| (i % 97) as f32 / 10.).collect();
+    c.bench_function("calculate_bin_values", |b| {
+        b.iter(|| black_box(calculate_bin_values(black_box(&values))))
+    });
+}
+
+criterion_group!(benches, load_reader, graph, bin_values);
+criterion_main!(benches);
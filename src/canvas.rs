@@ -0,0 +1,140 @@
+use crate::export::Pixels;
+use crate::text::stroke_text;
+use std::fmt::Write;
+
+// The same type as slint's, so that the window can show what's drawn without converting it.
+pub type Rgb8Pixel = rgb::RGB8;
+
+// Something the graph can be drawn on. Coordinates are in pixels with y increasing upwards
+// from the bottom.
+pub trait Canvas {
+    fn line_from_to(&mut self, from: (u32, u32), to: (u32, u32), colour: Rgb8Pixel);
+
+    // `text` with the bottom left corner of its first character at (x, y), stroked out of
+    // lines unless the canvas has text of its own.
+    fn text(&mut self, x: u32, y: u32, text: &str, scale: u32, colour: Rgb8Pixel) {
+        stroke_text(self, x, y, text, scale, colour);
+    }
+
+    // Draws a series line `width` pixels wide and smoothed, if the canvas can do that itself,
+    // and returns whether it did. Otherwise it's drawn a pixel at a time.
+    fn trace(
+        &mut self,
+        _from: (u32, u32),
+        _to: (u32, u32),
+        _colour: Rgb8Pixel,
+        _width: u32,
+    ) -> bool {
+        false
+    }
+}
+
+// Lines are stepped out a pixel at a time with Bresenham's algorithm. Pixels off the edge are
+// left out, so a line can run past it.
+impl Canvas for Pixels {
+    fn line_from_to(&mut self, from: (u32, u32), to: (u32, u32), colour: Rgb8Pixel) {
+        let (mut x, mut y) = (from.0 as i64, from.1 as i64);
+        let (x_end, y_end) = (to.0 as i64, to.1 as i64);
+        let (dx, dy) = ((x_end - x).abs(), -(y_end - y).abs());
+        let (x_step, y_step) = ((x_end - x).signum(), (y_end - y).signum());
+        let mut error = dx + dy;
+        loop {
+            if x < self.width as i64 && y < self.height as i64 {
+                let row = self.height as i64 - 1 - y;
+                self.pixels[(row * self.width as i64 + x) as usize] = colour;
+            }
+            if x == x_end && y == y_end {
+                break;
+            }
+            let doubled = 2 * error;
+            if doubled >= dy {
+                error += dy;
+                x += x_step;
+            }
+            if doubled <= dx {
+                error += dx;
+                y += y_step;
+            }
+        }
+    }
+}
+
+// Collects what's drawn as SVG elements, each line one pixel wide and each pixel a unit, so
+// that the drawing scales up without losing its edges.
+pub struct SvgCanvas {
+    width: u32,
+    height: u32,
+    elements: String,
+}
+
+impl SvgCanvas {
+    pub fn new(width: u32, height: u32, background: Rgb8Pixel) -> SvgCanvas {
+        let mut elements = String::new();
+        let _ = writeln!(
+            elements,
+            r#"<rect width="{width}" height="{height}" fill="{}"/>"#,
+            svg_colour(background)
+        );
+        SvgCanvas {
+            width,
+            height,
+            elements,
+        }
+    }
+
+    // SVG counts y down from the top, and a line through the middle of each pixel covers it.
+    fn point(&self, (x, y): (u32, u32)) -> (f32, f32) {
+        (
+            x as f32 + 0.5,
+            self.height.saturating_sub(y + 1) as f32 + 0.5,
+        )
+    }
+
+    pub fn finish(self) -> String {
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" \
+             viewBox=\"0 0 {0} {1}\" stroke-linecap=\"square\" stroke-width=\"1\">\n{2}</svg>\n",
+            self.width, self.height, self.elements
+        )
+    }
+}
+
+impl Canvas for SvgCanvas {
+    fn line_from_to(&mut self, from: (u32, u32), to: (u32, u32), colour: Rgb8Pixel) {
+        let ((x1, y1), (x2, y2)) = (self.point(from), self.point(to));
+        let _ = writeln!(
+            self.elements,
+            r#"<line x1="{x1}" y1="{y1}" x2="{x2}" y2="{y2}" stroke="{}"/>"#,
+            svg_colour(colour)
+        );
+    }
+
+    fn trace(&mut self, from: (u32, u32), to: (u32, u32), colour: Rgb8Pixel, width: u32) -> bool {
+        let ((x1, y1), (x2, y2)) = (self.point(from), self.point(to));
+        let _ = writeln!(
+            self.elements,
+            r#"<line x1="{x1}" y1="{y1}" x2="{x2}" y2="{y2}" stroke="{}" stroke-width="{}" stroke-linecap="round"/>"#,
+            svg_colour(colour),
+            width.max(1)
+        );
+        true
+    }
+
+    fn text(&mut self, x: u32, y: u32, text: &str, scale: u32, colour: Rgb8Pixel) {
+        let (x, y) = (x, self.height.saturating_sub(y));
+        let text = text
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;");
+        let _ = writeln!(
+            self.elements,
+            r#"<text x="{x}" y="{y}" font-family="monospace" font-size="{}" fill="{}">{text}</text>"#,
+            8 * scale,
+            svg_colour(colour)
+        );
+    }
+}
+
+fn svg_colour(colour: Rgb8Pixel) -> String {
+    format!("#{:02x}{:02x}{:02x}", colour.r, colour.g, colour.b)
+}
@@ -0,0 +1,360 @@
+use crate::canvas::Rgb8Pixel;
+use crate::graph::Theme;
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::str::FromStr;
+
+pub const BOAT_SPEED_COLOUR: Rgb8Pixel = Rgb8Pixel {
+    r: 0,
+    g: 0xff,
+    b: 0,
+};
+pub const WIND_SPEED_COLOUR: Rgb8Pixel = Rgb8Pixel {
+    r: 0xff,
+    g: 0xff,
+    b: 0xff,
+};
+pub const WIND_DIRECTION_COLOUR: Rgb8Pixel = Rgb8Pixel {
+    r: 0xff,
+    g: 0,
+    b: 0,
+};
+pub const TRUE_WIND_DIRECTION_COLOUR: Rgb8Pixel = Rgb8Pixel {
+    r: 0xff,
+    g: 0xa0,
+    b: 0,
+};
+pub const HEADING_COLOUR: Rgb8Pixel = Rgb8Pixel {
+    r: 0xff,
+    g: 0,
+    b: 0xff,
+};
+pub const WAYPOINT_BEARING_COLOUR: Rgb8Pixel = Rgb8Pixel {
+    r: 0,
+    g: 0xff,
+    b: 0xff,
+};
+pub const WAYPOINT_RANGE_COLOUR: Rgb8Pixel = Rgb8Pixel {
+    r: 0xff,
+    g: 0xff,
+    b: 0,
+};
+pub const SPEED_OVER_GROUND_COLOUR: Rgb8Pixel = Rgb8Pixel {
+    r: 0x80,
+    g: 0xc0,
+    b: 0,
+};
+pub const COURSE_OVER_GROUND_COLOUR: Rgb8Pixel = Rgb8Pixel {
+    r: 0xc0,
+    g: 0x80,
+    b: 0xff,
+};
+pub const DEPTH_COLOUR: Rgb8Pixel = Rgb8Pixel {
+    r: 0x40,
+    g: 0x60,
+    b: 0xff,
+};
+pub const WATER_TEMPERATURE_COLOUR: Rgb8Pixel = Rgb8Pixel {
+    r: 0xff,
+    g: 0x60,
+    b: 0xc0,
+};
+pub const VMG_COLOUR: Rgb8Pixel = Rgb8Pixel {
+    r: 0xff,
+    g: 0xd0,
+    b: 0x80,
+};
+pub const HEEL_COLOUR: Rgb8Pixel = Rgb8Pixel {
+    r: 0x60,
+    g: 0xd0,
+    b: 0xd0,
+};
+pub const TRIM_COLOUR: Rgb8Pixel = Rgb8Pixel {
+    r: 0xd0,
+    g: 0xd0,
+    b: 0x60,
+};
+pub const BAROMETER_COLOUR: Rgb8Pixel = Rgb8Pixel {
+    r: 0xc0,
+    g: 0xc0,
+    b: 0xc0,
+};
+
+pub const DERIVED_COLOURS: [Rgb8Pixel; 3] = [
+    Rgb8Pixel {
+        r: 0x80,
+        g: 0x80,
+        b: 0xff,
+    },
+    Rgb8Pixel {
+        r: 0xff,
+        g: 0x80,
+        b: 0x80,
+    },
+    Rgb8Pixel {
+        r: 0x80,
+        g: 0xff,
+        b: 0x80,
+    },
+];
+
+// A colour written as `#rrggbb`, with or without the `#`.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct HexColour(pub Rgb8Pixel);
+
+impl FromStr for HexColour {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let digits = text.strip_prefix('#').unwrap_or(text);
+        let component = |index: usize| {
+            digits
+                .get(index..index + 2)
+                .and_then(|a| u8::from_str_radix(a, 16).ok())
+        };
+        match (digits.len(), component(0), component(2), component(4)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(HexColour(Rgb8Pixel { r, g, b })),
+            _ => Err(format!("{text} isn't a colour like #ffa000")),
+        }
+    }
+}
+
+impl TryFrom<String> for HexColour {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        text.parse()
+    }
+}
+
+// The colours of the three main series, which can be changed to suit the screen or the viewer.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SeriesColours {
+    pub boat_speed: Rgb8Pixel,
+    pub wind_speed: Rgb8Pixel,
+    pub wind_direction: Rgb8Pixel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Palette {
+    #[default]
+    Default,
+    // Fully saturated colours that stay visible on a screen in sunlight.
+    HighContrast,
+    // From the Okabe-Ito palette, distinguishable with the common kinds of colour blindness.
+    ColourBlind,
+}
+
+impl Palette {
+    // The light theme gets darker versions that show up on white.
+    pub fn series_colours(&self, theme: Theme) -> SeriesColours {
+        match (self, theme) {
+            (Palette::Default, Theme::Light) => SeriesColours {
+                boat_speed: Rgb8Pixel {
+                    r: 0,
+                    g: 0x90,
+                    b: 0,
+                },
+                wind_speed: Rgb8Pixel {
+                    r: 0x20,
+                    g: 0x20,
+                    b: 0x20,
+                },
+                wind_direction: Rgb8Pixel {
+                    r: 0xd0,
+                    g: 0,
+                    b: 0,
+                },
+            },
+            (Palette::HighContrast, Theme::Light) => SeriesColours {
+                boat_speed: Rgb8Pixel {
+                    r: 0,
+                    g: 0x80,
+                    b: 0,
+                },
+                wind_speed: Rgb8Pixel {
+                    r: 0,
+                    g: 0,
+                    b: 0xd0,
+                },
+                wind_direction: Rgb8Pixel {
+                    r: 0xc0,
+                    g: 0,
+                    b: 0xc0,
+                },
+            },
+            (Palette::ColourBlind, Theme::Light) => SeriesColours {
+                boat_speed: Rgb8Pixel {
+                    r: 0xd5,
+                    g: 0x5e,
+                    b: 0,
+                },
+                wind_speed: Rgb8Pixel {
+                    r: 0,
+                    g: 0x72,
+                    b: 0xb2,
+                },
+                wind_direction: Rgb8Pixel {
+                    r: 0,
+                    g: 0x9e,
+                    b: 0x73,
+                },
+            },
+            (Palette::Default, Theme::Dark) => SeriesColours {
+                boat_speed: BOAT_SPEED_COLOUR,
+                wind_speed: WIND_SPEED_COLOUR,
+                wind_direction: WIND_DIRECTION_COLOUR,
+            },
+            (Palette::HighContrast, Theme::Dark) => SeriesColours {
+                boat_speed: Rgb8Pixel {
+                    r: 0,
+                    g: 0xff,
+                    b: 0,
+                },
+                wind_speed: Rgb8Pixel {
+                    r: 0,
+                    g: 0xc0,
+                    b: 0xff,
+                },
+                wind_direction: Rgb8Pixel {
+                    r: 0xff,
+                    g: 0,
+                    b: 0xff,
+                },
+            },
+            (Palette::ColourBlind, Theme::Dark) => SeriesColours {
+                boat_speed: Rgb8Pixel {
+                    r: 0xe6,
+                    g: 0x9f,
+                    b: 0,
+                },
+                wind_speed: Rgb8Pixel {
+                    r: 0x56,
+                    g: 0xb4,
+                    b: 0xe9,
+                },
+                wind_direction: Rgb8Pixel {
+                    r: 0,
+                    g: 0x9e,
+                    b: 0x73,
+                },
+            },
+        }
+    }
+}
+
+// How a channel's values are mapped onto the height of the graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    // Shares one scale with every other speed, from zero to the largest displayed speed.
+    Speed,
+    // An angle either side of the bow, folded into 0-180 and drawn downwards from the top.
+    HalfCircle,
+    // A compass bearing on a 0-360 scale drawn downwards from the top.
+    FullCircle,
+    // Scaled from zero to the channel's own largest displayed value.
+    Independent,
+    // Like Independent but drawn downwards from the top, as depths conventionally are.
+    Depth,
+    // Scaled to the channel's own largest magnitude either side of a zero line across the middle.
+    Signed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Channel {
+    BoatSpeed,
+    WindSpeed,
+    WindDirection,
+    TrueWindDirection,
+    Heading,
+    WaypointBearing,
+    WaypointRange,
+    SpeedOverGround,
+    CourseOverGround,
+    Depth,
+    WaterTemperature,
+    Vmg,
+    Heel,
+    Trim,
+    Barometer,
+    // Registered at runtime with `Data::with_derived`, indexing `Data::derived`.
+    #[value(skip)]
+    #[serde(skip)]
+    Derived(usize),
+}
+
+impl Channel {
+    pub const DEFAULT: [Channel; 3] = [
+        Channel::BoatSpeed,
+        Channel::WindSpeed,
+        Channel::WindDirection,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Channel::BoatSpeed => "Boat speed",
+            Channel::WindSpeed => "Wind speed",
+            Channel::WindDirection => "Wind direction",
+            Channel::TrueWindDirection => "True wind direction",
+            Channel::Heading => "Heading",
+            Channel::WaypointBearing => "Waypoint bearing",
+            Channel::WaypointRange => "Waypoint range",
+            Channel::SpeedOverGround => "Speed over ground",
+            Channel::CourseOverGround => "Course over ground",
+            Channel::Depth => "Depth",
+            Channel::WaterTemperature => "Water temperature",
+            Channel::Vmg => "VMG",
+            Channel::Heel => "Heel",
+            Channel::Trim => "Trim",
+            Channel::Barometer => "Barometer",
+            Channel::Derived(_) => "Derived",
+        }
+    }
+
+    pub fn colour(&self) -> Rgb8Pixel {
+        match self {
+            Channel::BoatSpeed => BOAT_SPEED_COLOUR,
+            Channel::WindSpeed => WIND_SPEED_COLOUR,
+            Channel::WindDirection => WIND_DIRECTION_COLOUR,
+            Channel::TrueWindDirection => TRUE_WIND_DIRECTION_COLOUR,
+            Channel::Heading => HEADING_COLOUR,
+            Channel::WaypointBearing => WAYPOINT_BEARING_COLOUR,
+            Channel::WaypointRange => WAYPOINT_RANGE_COLOUR,
+            Channel::SpeedOverGround => SPEED_OVER_GROUND_COLOUR,
+            Channel::CourseOverGround => COURSE_OVER_GROUND_COLOUR,
+            Channel::Depth => DEPTH_COLOUR,
+            Channel::WaterTemperature => WATER_TEMPERATURE_COLOUR,
+            Channel::Vmg => VMG_COLOUR,
+            Channel::Heel => HEEL_COLOUR,
+            Channel::Trim => TRIM_COLOUR,
+            Channel::Barometer => BAROMETER_COLOUR,
+            Channel::Derived(index) => DERIVED_COLOURS[index % DERIVED_COLOURS.len()],
+        }
+    }
+
+    pub fn axis(&self) -> Axis {
+        match self {
+            Channel::BoatSpeed | Channel::WindSpeed => Axis::Speed,
+            Channel::WindDirection => Axis::HalfCircle,
+            Channel::TrueWindDirection | Channel::Heading | Channel::WaypointBearing => {
+                Axis::FullCircle
+            }
+            Channel::SpeedOverGround => Axis::Speed,
+            Channel::CourseOverGround => Axis::FullCircle,
+            Channel::Depth => Axis::Depth,
+            Channel::WaterTemperature => Axis::Independent,
+            Channel::Vmg | Channel::Heel | Channel::Trim => Axis::Signed,
+            Channel::Barometer => Axis::Independent,
+            Channel::WaypointRange | Channel::Derived(_) => Axis::Independent,
+        }
+    }
+
+    // Channels measured by the wind instrument, which are split by source when several
+    // instruments are merged.
+    pub fn per_source(&self) -> bool {
+        matches!(self, Channel::WindSpeed | Channel::WindDirection)
+    }
+}
@@ -0,0 +1,66 @@
+use crate::datapoints::{Data, DataPoint};
+use chrono::{DateTime, TimeDelta, Utc};
+
+// Each estimate averages the datapoints this far back, so that the noise in the GPS and the
+// through-water instruments mostly cancels out.
+pub const CURRENT_WINDOW_SECONDS: i64 = 60;
+
+impl Data {
+    // The set (degrees true the water is flowing towards) and drift (knots) of the current at
+    // each datapoint with both a ground track and a through-water track. The current is the
+    // difference between the velocity over the ground and the velocity through the water.
+    pub fn current_estimate(&self) -> Vec<(DateTime<Utc>, f32, f32)> {
+        let velocities: Vec<(DateTime<Utc>, (f32, f32))> = self
+            .data
+            .iter()
+            .filter_map(|a| current_velocity(a).map(|velocity| (a.timestamp, velocity)))
+            .collect();
+        let window = TimeDelta::seconds(CURRENT_WINDOW_SECONDS);
+        let mut estimates = Vec::with_capacity(velocities.len());
+        let mut oldest = 0;
+        let mut total = (0., 0.);
+        for (timestamp, velocity) in &velocities {
+            total = (total.0 + velocity.0, total.1 + velocity.1);
+            while *timestamp - velocities[oldest].0 > window {
+                let dropped = velocities[oldest].1;
+                total = (total.0 - dropped.0, total.1 - dropped.1);
+                oldest += 1;
+            }
+            let count = (estimates.len() + 1 - oldest) as f32;
+            let (set, drift) = set_and_drift((total.0 / count, total.1 / count));
+            estimates.push((*timestamp, set, drift));
+        }
+        estimates
+    }
+
+    // The set and drift of the current averaged over the whole session, or None without both
+    // ground and through-water tracks.
+    pub fn mean_current(&self) -> Option<(f32, f32)> {
+        let (count, total) = self
+            .data
+            .iter()
+            .filter_map(current_velocity)
+            .fold((0, (0., 0.)), |(count, total), velocity| {
+                (count + 1, (total.0 + velocity.0, total.1 + velocity.1))
+            });
+        (count > 0).then(|| set_and_drift((total.0 / count as f32, total.1 / count as f32)))
+    }
+}
+
+// The current's (east, north) velocity in knots at one datapoint: its speed and course over
+// the ground less its speed through the water along its heading.
+fn current_velocity(point: &DataPoint) -> Option<(f32, f32)> {
+    let (boatspeed, sog, cog, heading) = (point.boatspeed?, point.sog?, point.cog?, point.heading?);
+    let (cog, heading) = (cog.to_radians(), heading.to_radians());
+    Some((
+        sog * cog.sin() - boatspeed * heading.sin(),
+        sog * cog.cos() - boatspeed * heading.cos(),
+    ))
+}
+
+fn set_and_drift(velocity: (f32, f32)) -> (f32, f32) {
+    (
+        velocity.0.atan2(velocity.1).to_degrees().rem_euclid(360.),
+        velocity.0.hypot(velocity.1),
+    )
+}
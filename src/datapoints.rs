@@ -1,327 +1,1350 @@
-use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
-use libgraphicimage_slint::GraphicImage;
+use crate::channels::Channel;
+use crate::overview::{Overview, OVERVIEW_WIDTH};
+use crate::profile::Profile;
+use crate::units::SpeedUnit;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeDelta, Utc};
+use flate2::read::MultiGzDecoder;
 use libnmea0183::base::{DateTimeError, Nmea0183Base};
 use libnmea0183::classify;
-use libnmea0183::Nmea0183::{BWC, BWR, GGA, GRS, GST, GXA, MWV, RMC, TRF, VBW, VHW, ZDA, ZFO, ZTG};
-use slint::private_unstable_api::re_exports::euclid::approxeq::ApproxEq;
-use slint::{Image, Rgb8Pixel};
-use std::cmp::Ordering;
+use libnmea0183::Nmea0183::{
+    BWC, BWR, DBT, DPT, GGA, GLL, GRS, GST, GXA, HDG, HDM, HDT, MTW, MWD, MWV, RMB, RMC, TRF, VBW,
+    VHW, VLW, VTG, VWR, ZDA, ZFO, ZTG,
+};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
-use std::io::{stdin, BufRead, BufReader};
+use std::io::{self, stdin, BufRead, BufReader, ErrorKind, Read};
+use std::path::Path;
 use std::process::exit;
+use std::rc::Rc;
+use std::time::Instant;
 
-const BOAT_SPEED_COLOUR: Rgb8Pixel = Rgb8Pixel {
-    r: 0,
-    g: 0xff,
-    b: 0,
-};
-const WIND_SPEED_COLOUR: Rgb8Pixel = Rgb8Pixel {
-    r: 0xff,
-    g: 0xff,
-    b: 0xff,
-};
-const WIND_DIRECTION_COLOUR: Rgb8Pixel = Rgb8Pixel {
-    r: 0xff,
-    g: 0,
-    b: 0,
-};
+// The first line of a file written by `Data::to_csv`.
+pub const CSV_HEADER: &str = "timestamp,boatspeed,windspeed,winddirection";
+
+const METRES_PER_FOOT: f32 = 0.3048;
+const METRES_PER_FATHOM: f32 = 1.8288;
 
-#[derive(Debug, Clone)]
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+// Serialized with the timestamp in RFC3339 and missing readings as null.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DataPoint {
     pub timestamp: DateTime<Utc>,
-    pub boatspeed: f32,
-    pub windspeed: f32,
-    pub winddirection: f32,
+    pub boatspeed: Option<f32>,
+    pub windspeed: Option<f32>,
+    pub winddirection: Option<f32>,
+    // Talker ID of the wind instrument, only recorded when loading with `merge_sources`.
+    pub source: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    // Bearing (degrees true) and range (nautical miles) to the active waypoint, from RMB.
+    pub waypoint_bearing: Option<f32>,
+    pub waypoint_range: Option<f32>,
+    // Degrees true, from HDT, or from HDG or HDM corrected by the variation.
+    pub heading: Option<f32>,
+    // Magnetic variation in degrees, east positive, from the most recent RMC or HDG that gave
+    // one. Used to turn magnetic headings and wind directions into true ones.
+    pub variation: Option<f32>,
+    // Speed (knots) and course (degrees true) over the ground, from VTG.
+    pub sog: Option<f32>,
+    pub cog: Option<f32>,
+    // Metres below the transducer, from DBT or DPT.
+    pub depth: Option<f32>,
+    // Degrees Celsius, from MTW.
+    pub water_temp: Option<f32>,
+    // Total distance through the water in nautical miles, from the log's odometer in VLW.
+    pub log_distance: Option<f32>,
+    // Degrees, from XDR angular displacement transducers: heel to starboard and bow up positive
+    // as most instruments report them.
+    pub heel: Option<f32>,
+    pub trim: Option<f32>,
+    // Barometric pressure in hectopascals, from an XDR pressure transducer.
+    pub baro: Option<f32>,
+    // Compass direction (degrees true) the true wind is blowing from, as reported by MWD. Wind
+    // from MWD takes precedence over wind from MWV in the same datapoint.
+    pub wind_from: Option<f32>,
+    // Relative wind reported by MWV, kept until there is a boat speed to turn it into true wind.
+    pub apparent_windspeed: Option<f32>,
+    pub apparent_winddirection: Option<f32>,
 }
 
 impl DataPoint {
     pub fn new() -> DataPoint {
         DataPoint {
             timestamp: DateTime::default(),
-            boatspeed: 0.,
-            windspeed: 0.,
-            winddirection: 0.,
+            boatspeed: None,
+            windspeed: None,
+            winddirection: None,
+            source: None,
+            latitude: None,
+            longitude: None,
+            waypoint_bearing: None,
+            waypoint_range: None,
+            heading: None,
+            variation: None,
+            sog: None,
+            cog: None,
+            depth: None,
+            water_temp: None,
+            log_distance: None,
+            heel: None,
+            trim: None,
+            baro: None,
+            wind_from: None,
+            apparent_windspeed: None,
+            apparent_winddirection: None,
+        }
+    }
+
+    pub fn get(&self, channel: Channel) -> Option<f32> {
+        match channel {
+            Channel::BoatSpeed => self.boatspeed,
+            Channel::WindSpeed => self.windspeed,
+            Channel::WindDirection => self.winddirection,
+            Channel::TrueWindDirection => self.true_wind_direction(),
+            Channel::Heading => self.heading,
+            Channel::WaypointBearing => self.waypoint_bearing,
+            Channel::WaypointRange => self.waypoint_range,
+            Channel::SpeedOverGround => self.sog,
+            Channel::CourseOverGround => self.cog,
+            Channel::Depth => self.depth,
+            Channel::WaterTemperature => self.water_temp,
+            Channel::Vmg => self.vmg(),
+            Channel::Heel => self.heel,
+            Channel::Trim => self.trim,
+            Channel::Barometer => self.baro,
+            Channel::Derived(_) => None,
         }
     }
+
+    // The compass direction the true wind is blowing from. Unless an instrument reported it
+    // directly, this needs the boat's heading to turn the bow-relative true wind angle into an
+    // absolute bearing. Without a heading it falls back to the bow-relative angle itself.
+    pub fn true_wind_direction(&self) -> Option<f32> {
+        if self.wind_from.is_some() {
+            return self.wind_from;
+        }
+        let winddirection = self.winddirection?;
+        Some(match self.heading {
+            Some(heading) => (heading + winddirection).rem_euclid(360.),
+            None => winddirection,
+        })
+    }
+
+    // Velocity made good towards the wind, negative when sailing away from it.
+    pub fn vmg(&self) -> Option<f32> {
+        Some(self.boatspeed? * self.winddirection?.to_radians().cos())
+    }
 }
 
-#[derive(Debug, Clone)]
+impl Default for DataPoint {
+    fn default() -> Self {
+        DataPoint::new()
+    }
+}
+
+// Readings from the lines read so far that haven't become a datapoint yet, carried from one
+// line to the next.
+#[derive(Debug, Default)]
+pub(crate) struct PendingReadings {
+    datapoint: DataPoint,
+    source_winds: BTreeMap<String, (f32, f32)>,
+    // Of the last line read, counted from 1.
+    line_number: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LoadOptions {
+    // Keep wind readings from different talkers as separate datapoints instead of letting the
+    // most recent one overwrite the others.
+    pub merge_sources: bool,
+    // Whether each instrument's zero readings mean "no reading" rather than a genuine zero.
+    pub wind_zero_missing: bool,
+    pub boat_zero_missing: bool,
+    // Fail on the first line that can't be parsed instead of skipping it.
+    pub strict: bool,
+    // Skip sentences whose `*hh` checksum doesn't match instead of only counting them.
+    #[serde(default)]
+    pub reject_bad_checksums: bool,
+}
+
+impl LoadOptions {
+    fn has_boatspeed(&self, datapoint: &DataPoint) -> bool {
+        is_present(datapoint.boatspeed, self.boat_zero_missing)
+    }
+
+    fn has_wind(&self, datapoint: &DataPoint) -> bool {
+        is_present(datapoint.windspeed, self.wind_zero_missing) && datapoint.winddirection.is_some()
+    }
+
+    fn has_reading(&self, datapoint: &DataPoint) -> bool {
+        self.has_boatspeed(datapoint)
+            || is_present(datapoint.windspeed, self.wind_zero_missing)
+            || datapoint.winddirection.is_some()
+    }
+}
+
+#[derive(Debug)]
+pub enum DataError {
+    Io(std::io::Error),
+    Parse {
+        line_number: usize,
+        text: String,
+        reason: String,
+    },
+    Empty,
+}
+
+impl fmt::Display for DataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataError::Io(e) => write!(f, "{e}"),
+            DataError::Parse {
+                line_number,
+                text,
+                reason,
+            } => write!(f, "line {line_number}: {reason}: {text}"),
+            DataError::Empty => write!(f, "no input"),
+        }
+    }
+}
+
+impl std::error::Error for DataError {}
+
+impl From<std::io::Error> for DataError {
+    fn from(e: std::io::Error) -> Self {
+        DataError::Io(e)
+    }
+}
+
+// Only what came from the log is serialized. Anything recalculated or registered at runtime is
+// left out and rebuilt after loading.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Data {
     pub data: Vec<DataPoint>,
+    pub load_options: LoadOptions,
+    // The files the datapoints were loaded from, or "stdin".
+    #[serde(skip)]
+    pub sources: Vec<String>,
+    // Rebuilt whenever datapoints are loaded.
+    #[serde(skip)]
+    pub overview: Overview,
+    // How many wind angles arrived outside 0-360 and had to be wrapped.
+    pub normalized_angles: usize,
+    // How many sentences had a checksum that didn't match, whether or not they were skipped.
+    #[serde(default)]
+    pub bad_checksums: usize,
+    #[serde(skip)]
+    pub profile: Profile,
+    #[serde(skip)]
+    pub derived: Vec<DerivedChannel>,
+    // Counts of each sentence type that was parsed but isn't used for anything.
+    pub unhandled_sentences: BTreeMap<String, usize>,
+    pub lines_read: usize,
+    // Lines that couldn't be read or parsed and were left out.
+    pub skipped_lines: Vec<SkippedLine>,
+    // Datapoints as they were before `despike` removed a reading from them.
+    #[serde(skip)]
+    pub spikes: Vec<DataPoint>,
+    // The date from the most recent sentence that carried one, for sentences that only carry
+    // the time of day.
+    #[serde(skip)]
+    last_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedLine {
+    // Counted from 1 within the file or stream the line came from.
+    pub line_number: usize,
+    pub text: String,
+    pub reason: String,
+}
+
+// A channel computed from each datapoint on demand, registered with `Data::with_derived`.
+#[derive(Clone)]
+pub struct DerivedChannel {
+    pub name: String,
+    pub function: Rc<dyn Fn(&DataPoint) -> Option<f32>>,
+}
+
+impl fmt::Debug for DerivedChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DerivedChannel")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for Data {
+    fn default() -> Self {
+        Data::new()
+    }
+}
+
+impl<'a> IntoIterator for &'a Data {
+    type Item = &'a DataPoint;
+    type IntoIter = std::slice::Iter<'a, DataPoint>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.data.iter()
+    }
 }
 
 impl Data {
     pub fn new() -> Data {
-        Data { data: Vec::new() }
+        Data {
+            data: Vec::new(),
+            load_options: LoadOptions::default(),
+            sources: Vec::new(),
+            overview: Overview::default(),
+            normalized_angles: 0,
+            bad_checksums: 0,
+            profile: Profile::default(),
+            derived: Vec::new(),
+            unhandled_sentences: BTreeMap::new(),
+            lines_read: 0,
+            skipped_lines: Vec::new(),
+            spikes: Vec::new(),
+            last_date: None,
+        }
+    }
+
+    pub fn from_datapoints(data: Vec<DataPoint>) -> Data {
+        Data {
+            overview: Overview::new(&data, OVERVIEW_WIDTH),
+            data,
+            ..Data::new()
+        }
+    }
+
+    // Removes boat and wind speed readings that jump more than `max_delta` knots away from the
+    // readings either side of them and straight back, such as a paddlewheel reporting 99 knots
+    // for a single sentence, which would otherwise flatten everything else on the speed axis.
+    // The rest of each datapoint is kept.
+    pub fn despike(&mut self, max_delta: f32) {
+        let boat_spikes = find_spikes(self.data.iter().map(|a| a.boatspeed), max_delta);
+        let wind_spikes = find_spikes(self.data.iter().map(|a| a.windspeed), max_delta);
+        let mut spiked: Vec<usize> = boat_spikes.iter().chain(&wind_spikes).copied().collect();
+        spiked.sort_unstable();
+        spiked.dedup();
+        for index in spiked {
+            self.spikes.push(self.data[index].clone());
+        }
+        for index in boat_spikes {
+            self.data[index].boatspeed = None;
+        }
+        for index in wind_spikes {
+            self.data[index].windspeed = None;
+        }
+        self.overview = Overview::new(&self.data, OVERVIEW_WIDTH);
     }
 
-    pub fn load_filename(filename: Option<String>) -> Data {
-        let reader: Box<dyn BufRead> = match filename {
-            None => {
-                println!("Loading from stdin.");
-                Box::new(BufReader::new(stdin()))
+    // Brings a despiked copy of `raw` up to date with the datapoints appended to `raw` since,
+    // removing the same readings `despike` would from the whole of it. Only the new datapoints
+    // and the two readings of each speed before them are looked at, since the last of those
+    // had nothing after it to be a spike against until now. A reading removed from before the
+    // new datapoints stays in the overview.
+    pub fn despike_appended(&mut self, raw: &Data, max_delta: f32) {
+        let from = self.data.len();
+        let boat_spikes = find_appended_spikes(&raw.data, from, |a| a.boatspeed, max_delta);
+        let wind_spikes = find_appended_spikes(&raw.data, from, |a| a.windspeed, max_delta);
+        self.data.extend_from_slice(&raw.data[from..]);
+        let mut spiked: Vec<usize> = boat_spikes.iter().chain(&wind_spikes).copied().collect();
+        spiked.sort_unstable();
+        spiked.dedup();
+        for index in spiked {
+            // Unless it's already there for the other speed.
+            let point = &self.data[index];
+            if point.boatspeed == raw.data[index].boatspeed
+                && point.windspeed == raw.data[index].windspeed
+            {
+                self.spikes.push(raw.data[index].clone());
             }
-            Some(filename) => match fs::File::open(filename.clone()) {
+        }
+        for index in boat_spikes {
+            self.data[index].boatspeed = None;
+        }
+        for index in wind_spikes {
+            self.data[index].windspeed = None;
+        }
+        self.overview.extend(&self.data, from);
+    }
+
+    // A copy of this data with boat speed, wind speed and wind angle passed through a first order
+    // low-pass filter with a time constant of `tau` seconds. Wind angles are smoothed as unit
+    // vectors so that readings either side of 0 average to 0 rather than 180. Expects the data to
+    // be sorted.
+    pub fn smoothed(&self, tau: f32) -> Data {
+        let mut data = self.clone();
+        let mut boatspeed: Option<f32> = None;
+        let mut windspeed: Option<f32> = None;
+        let mut wind_vector: Option<(f32, f32)> = None;
+        let mut previous_time: Option<DateTime<Utc>> = None;
+        for point in &mut data.data {
+            let elapsed = previous_time
+                .map(|a| (point.timestamp - a).num_milliseconds() as f32 / 1000.)
+                .unwrap_or(0.);
+            previous_time = Some(point.timestamp);
+            // How far each new reading pulls the filtered value towards it.
+            let alpha = if tau > 0. {
+                1. - (-elapsed / tau).exp()
+            } else {
+                1.
+            };
+            let filter = |state: &mut Option<f32>, value: &mut Option<f32>| {
+                let Some(reading) = *value else {
+                    return;
+                };
+                let filtered = state.map_or(reading, |a| a + alpha * (reading - a));
+                *state = Some(filtered);
+                *value = Some(filtered);
+            };
+            filter(&mut boatspeed, &mut point.boatspeed);
+            filter(&mut windspeed, &mut point.windspeed);
+            if let Some(direction) = point.winddirection {
+                let radians = direction.to_radians();
+                let (sin, cos) = match wind_vector {
+                    Some((sin, cos)) => (
+                        sin + alpha * (radians.sin() - sin),
+                        cos + alpha * (radians.cos() - cos),
+                    ),
+                    None => (radians.sin(), radians.cos()),
+                };
+                wind_vector = Some((sin, cos));
+                point.winddirection = Some(sin.atan2(cos).to_degrees().rem_euclid(360.));
+            }
+        }
+        data.overview = Overview::new(&data.data, OVERVIEW_WIDTH);
+        data
+    }
+
+    // A copy of this data with an extra channel computed by `function`, which can then be
+    // plotted and exported through `Channel::Derived` like any other.
+    pub fn with_derived<F: Fn(&DataPoint) -> Option<f32> + 'static>(
+        &self,
+        name: &str,
+        function: F,
+    ) -> Data {
+        let mut data = self.clone();
+        data.derived.push(DerivedChannel {
+            name: name.to_string(),
+            function: Rc::new(function),
+        });
+        data
+    }
+
+    pub fn derived_channel(&self, name: &str) -> Option<Channel> {
+        self.derived
+            .iter()
+            .position(|a| a.name == name)
+            .map(Channel::Derived)
+    }
+
+    pub fn value(&self, point: &DataPoint, channel: Channel) -> Option<f32> {
+        match channel {
+            Channel::Derived(index) => self.derived.get(index).and_then(|a| (a.function)(point)),
+            _ => point.get(channel),
+        }
+    }
+
+    pub fn channel_name(&self, channel: Channel) -> String {
+        match channel {
+            Channel::Derived(index) => self
+                .derived
+                .get(index)
+                .map(|a| a.name.clone())
+                .unwrap_or_default(),
+            _ => channel.name().to_string(),
+        }
+    }
+
+    // Loads and merges every file in turn, or stdin if there are none. The result is sorted by
+    // time so that files given out of order still graph correctly.
+    pub fn load_filenames(filenames: &[String], load_options: LoadOptions) -> Data {
+        let mut data = Data::new();
+        data.load_options = load_options;
+        data.sources = filenames.to_vec();
+        if filenames.is_empty() {
+            info!("Loading from stdin.");
+            data.sources.push("stdin".to_string());
+            data.load_or_exit(decompressing_reader(stdin()), "stdin");
+        }
+        for filename in filenames {
+            match fs::File::open(filename) {
                 Ok(file) => {
-                    println!("Loading from {filename}");
-                    Box::new(BufReader::new(file))
+                    info!("Loading from {filename}");
+                    data.load_or_exit(decompressing_reader(file), filename);
                 }
                 Err(e) => {
-                    eprintln!("{e:?}");
+                    error!("{filename}: {e}");
                     exit(-1);
                 }
-            },
-        };
+            }
+        }
+        data.sort();
+        if !data.skipped_lines.is_empty() {
+            info!(
+                "Skipped {} of {} lines",
+                data.skipped_lines.len(),
+                data.lines_read
+            );
+        }
+        if data.bad_checksums > 0 {
+            let action = if data.load_options.reject_bad_checksums {
+                "skipped"
+            } else {
+                "kept"
+            };
+            warn!(
+                "{} sentences had bad checksums and were {action}",
+                data.bad_checksums
+            );
+        }
+        data
+    }
 
+    // Loads a single log, reporting failures rather than exiting, for reading a file chosen
+    // while the program is running.
+    pub fn load_file(path: &Path, load_options: LoadOptions) -> Result<Data, DataError> {
         let mut data = Data::new();
-        data.load_reader(reader);
+        data.load_options = load_options;
+        data.load_reader(decompressing_reader(fs::File::open(path)?))?;
+        data.sort();
+        data.sources = vec![path.display().to_string()];
+        Ok(data)
+    }
+
+    fn load_or_exit(&mut self, reader: Box<dyn BufRead>, name: &str) {
+        match self.load_reader(reader) {
+            Ok(()) => {}
+            // An empty file among several shouldn't stop the others loading.
+            Err(DataError::Empty) => warn!("{name} is empty"),
+            Err(e) => {
+                error!("{name}: {e}");
+                exit(-1);
+            }
+        }
+    }
+
+    // The earliest and latest timestamps, or None if there are no datapoints.
+    pub fn time_range(&self) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        self.data
+            .iter()
+            .map(|a| (a.timestamp, a.timestamp))
+            .reduce(|a, b| (a.0.min(b.0), a.1.max(b.1)))
+    }
+
+    // The time range to show initially: all of the data, or the last hour if there isn't any.
+    pub fn display_range(&self) -> (DateTime<Utc>, DateTime<Utc>) {
+        self.time_range().unwrap_or_else(|| {
+            let now = Utc::now();
+            (now - TimeDelta::hours(1), now)
+        })
+    }
+
+    // The datapoints from `start` to `end` inclusive, found by binary search. Expects the data to
+    // be sorted by timestamp, as it is once loaded.
+    pub fn range(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> &[DataPoint] {
+        let low = self.data.partition_point(|a| a.timestamp < start);
+        let high = self.data.partition_point(|a| a.timestamp <= end);
+        &self.data[low..high.max(low)]
+    }
+
+    // The datapoints from `start` to `end` in time order, sorting them first if they were built
+    // out of order.
+    pub(crate) fn sorted_range(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<&DataPoint> {
+        if self.data.is_sorted_by_key(|a| a.timestamp) {
+            return self.range(start, end).iter().collect();
+        }
+        let mut window: Vec<&DataPoint> = self
+            .into_iter()
+            .filter(|a| a.timestamp >= start && a.timestamp <= end)
+            .collect();
+        window.sort_by_key(|a| a.timestamp);
+        window
+    }
+
+    // The datapoint closest in time to `time`. Expects the data to be sorted.
+    pub fn nearest(&self, time: DateTime<Utc>) -> Option<&DataPoint> {
+        let index = self.data.partition_point(|a| a.timestamp < time);
+        let before = index.checked_sub(1).and_then(|i| self.data.get(i));
+        let after = self.data.get(index);
+        match (before, after) {
+            (Some(before), Some(after)) => {
+                Some(if time - before.timestamp <= after.timestamp - time {
+                    before
+                } else {
+                    after
+                })
+            }
+            (before, after) => before.or(after),
+        }
+    }
+
+    // Splits the datapoints wherever consecutive ones are more than `gap` apart, such as
+    // overnight on the mooring when a logger records several outings into one file. Expects the
+    // data to be sorted.
+    pub fn sessions(&self, gap: TimeDelta) -> Vec<Data> {
+        self.data
+            .chunk_by(|a, b| b.timestamp - a.timestamp <= gap)
+            .map(|a| Data {
+                load_options: self.load_options.clone(),
+                derived: self.derived.clone(),
+                ..Data::from_datapoints(a.to_vec())
+            })
+            .collect()
+    }
+
+    // A copy moved in time to start when `other` does, so that two sails can be drawn over each
+    // other.
+    pub fn aligned_to(&self, other: &Data) -> Data {
+        let mut data = self.clone();
+        if let (Some((start, _)), Some((other_start, _))) = (self.time_range(), other.time_range())
+        {
+            let offset = other_start - start;
+            for point in &mut data.data {
+                point.timestamp += offset;
+            }
+        }
         data
     }
 
-    pub fn load_reader(&mut self, reader: Box<dyn BufRead>) {
-        let mut dp = DataPoint::new();
+    pub fn sort(&mut self) {
+        self.data.sort_by_key(|a| a.timestamp);
+    }
 
-        for line in reader.lines() {
-            match line {
-                Err(e) => {
-                    eprintln!("{e:?}");
-                    exit(-1);
+    pub fn load_reader(&mut self, mut reader: Box<dyn BufRead>) -> Result<(), DataError> {
+        // Datapoints cached by `Data::to_csv` or `Data::to_json` load far faster than the NMEA
+        // they came from.
+        let start = reader.fill_buf()?;
+        if start.starts_with(CSV_HEADER.as_bytes()) {
+            return self.load_csv(reader);
+        }
+        if start.trim_ascii_start().starts_with(b"{") {
+            return self.load_json(reader);
+        }
+
+        let mut pending = PendingReadings::default();
+        let mut lines = reader.lines();
+        loop {
+            let reading = Instant::now();
+            let Some(line) = lines.next() else {
+                break;
+            };
+            let parsing = Instant::now();
+            self.profile.record("read", parsing - reading);
+            self.read_line(&mut pending, line, parsing)?;
+        }
+
+        let timestamp = pending.datapoint.timestamp;
+        self.complete_datapoint(&mut pending.datapoint, &mut pending.source_winds, timestamp);
+
+        let overview = Instant::now();
+        self.overview = Overview::new(&self.data, OVERVIEW_WIDTH);
+        self.profile.record("overview", overview.elapsed());
+
+        if pending.line_number == 0 {
+            return Err(DataError::Empty);
+        }
+        Ok(())
+    }
+
+    // Adds one line of NMEA, as it was read from a log or a live source, to `pending`. Text
+    // that isn't valid UTF-8 only spoils its own line. `parsing` is when reading the line
+    // finished, for the profile.
+    pub(crate) fn read_line(
+        &mut self,
+        pending: &mut PendingReadings,
+        line: io::Result<String>,
+        parsing: Instant,
+    ) -> Result<(), DataError> {
+        pending.line_number += 1;
+        self.lines_read += 1;
+        match line {
+            Err(e) if e.kind() == ErrorKind::InvalidData => {
+                self.skip_line(pending.line_number, String::new(), format!("{e:?}"))
+            }
+            Err(e) => Err(DataError::Io(e)),
+            Ok(line) => self.process_line(
+                &mut pending.datapoint,
+                &mut pending.source_winds,
+                pending.line_number,
+                line,
+                parsing,
+            ),
+        }
+    }
+
+    // Parses one line of NMEA into `dp`, which collects readings until the time moves on and
+    // they become a datapoint.
+    fn process_line(
+        &mut self,
+        dp: &mut DataPoint,
+        source_winds: &mut BTreeMap<String, (f32, f32)>,
+        line_number: usize,
+        line: String,
+        parsing: Instant,
+    ) -> Result<(), DataError> {
+        if let Some(reason) = checksum_error(&line) {
+            self.bad_checksums += 1;
+            if self.load_options.reject_bad_checksums {
+                self.profile.record("parse", parsing.elapsed());
+                return self.skip_line(line_number, line, reason);
+            }
+        }
+        let parsed = Nmea0183Base::from_string(&line);
+        let assembling = Instant::now();
+        self.profile.record("parse", assembling - parsing);
+        match parsed {
+            Err(e) => {
+                self.skip_line(line_number, line.clone(), format!("{e:?}"))?;
+            }
+            Ok(base) => {
+                let previous_time = dp.timestamp;
+                // XDR's measurements are read straight from its fields, as what each one is
+                // depends on the transducer's type and name.
+                let used = if sentence_type(&line) == "XDR" {
+                    process_xdr(dp, &line)
+                } else {
+                    self.process_nmea(dp, base)
+                };
+                if !used {
+                    *self
+                        .unhandled_sentences
+                        .entry(sentence_type(&line))
+                        .or_insert(0) += 1;
+                }
+                self.resolve_true_wind(dp);
+                if self.load_options.merge_sources {
+                    self.keep_source_wind(dp, source_winds, talker_id(&line));
+                }
+                if dp.timestamp != previous_time {
+                    // Whatever was read before the time moved on belongs to the previous time,
+                    // even if some instruments didn't report.
+                    self.complete_datapoint(dp, source_winds, previous_time);
                 }
-                Ok(line) => match Nmea0183Base::from_string(&line) {
-                    Err(e) => {
-                        eprintln!("{e:?}");
-                        exit(-1);
-                    }
-                    Ok(base) => {
-                        self.process_nmea(&mut dp, base);
-                        if dp.windspeed > 0.
-                            && dp.boatspeed > 0.
-                            && dp.winddirection != 0.
-                            && dp.timestamp != DateTime::<Utc>::default()
-                        {
-                            let current_date = dp.timestamp.clone();
-                            self.data.push(dp);
-                            dp = DataPoint {
-                                timestamp: current_date,
-                                boatspeed: 0.,
-                                windspeed: 0.,
-                                winddirection: 0.,
-                            }
-                        }
-                    }
-                },
             }
         }
+        self.profile.record("assemble", assembling.elapsed());
+        Ok(())
     }
 
-    pub fn graph(
-        &self,
-        width: u32,
-        height: u32,
-        start_datetime: DateTime<Utc>,
-        end_datetime: DateTime<Utc>,
-    ) -> Image {
-        let mut graphicimage = GraphicImage::new(width, height);
-        if self.data.len() >= 2 {
-            let (
-                earliest_time,
-                latest_time,
-                _,
-                largest_boatspeed,
-                _,
-                largest_windspeed,
-            ) = self
-                .data
-                .iter()
-                .filter(|a| a.timestamp >= start_datetime && a.timestamp <= end_datetime)
-                .map(|a| {
-                    (
-                        a.timestamp,
-                        a.timestamp,
-                        a.boatspeed,
-                        a.boatspeed,
-                        a.windspeed,
-                        a.windspeed,
-                    )
-                })
-                .reduce(|a, b| {
-                    (
-                        a.0.min(b.0),
-                        a.1.max(b.1),
-                        a.2.min(b.2),
-                        a.3.max(b.3),
-                        a.4.min(b.4),
-                        a.5.max(b.5),
-                    )
-                })
-                .unwrap();
-            let speed_ratio = (height - 1) as f32 / ((largest_boatspeed.max(largest_windspeed)).floor() + 1f32);
-            let direction_ratio = height as f32 / 180f32;
-
-            let time_range_milliseconds = (latest_time.min(end_datetime) - earliest_time).num_milliseconds() as f32;
-            let bin_time_range =
-                TimeDelta::milliseconds((time_range_milliseconds / width as f32) as i64);
-            let mut bin_start_time = earliest_time.max(start_datetime);
-            let stop_time = latest_time.min(end_datetime);
-
-            let mut x = 0;
-
-            while bin_start_time <= stop_time {
-                let bin_end_time = bin_start_time + bin_time_range;
-                // Boat speeds
-                let bin_data_set: Vec<&DataPoint> = self
-                    .data
-                    .iter()
-                    .filter(|a| a.timestamp >= bin_start_time && a.timestamp < bin_end_time)
-                    .collect();
-                let bin_boatspeeds: Vec<f32> = bin_data_set.iter().map(|a| a.boatspeed).collect();
-                let bin_windspeeds: Vec<f32> = bin_data_set.iter().map(|a| a.windspeed).collect();
-                let bin_winddirections: Vec<f32> = bin_data_set
-                    .iter()
-                    .map(|a| {
-                        if a.winddirection > 180f32 {
-                            360f32 - a.winddirection
-                        } else {
-                            a.winddirection
-                        }
-                    })
-                    .collect();
-
-                let (bin_low_boatspeed, bin_high_boatspeed) = calculate_bin_values(&bin_boatspeeds);
-                let (bin_low_windspeed, bin_high_windspeed) = calculate_bin_values(&bin_windspeeds);
-                let (bin_low_winddirection, bin_high_winddirection) =
-                    calculate_bin_values(&bin_winddirections);
-
-                let bin_boatspeed_high_y = (bin_high_boatspeed * speed_ratio) as u32;
-                let bin_windspeed_high_y = (bin_high_windspeed * speed_ratio) as u32;
-                let bin_winddirection_high_y =
-                    height - (bin_low_winddirection * direction_ratio) as u32;
-
-                let bin_boatspeed_low_y = (bin_low_boatspeed * speed_ratio) as u32;
-                let bin_windspeed_low_y = (bin_low_windspeed * speed_ratio) as u32;
-                let bin_winddirection_low_y =
-                    height - (bin_high_winddirection * direction_ratio) as u32;
-
-                for item in [
-                    (bin_boatspeed_low_y, bin_boatspeed_high_y, BOAT_SPEED_COLOUR),
-                    (bin_windspeed_low_y, bin_windspeed_high_y, WIND_SPEED_COLOUR),
-                    (
-                        bin_winddirection_low_y,
-                        bin_winddirection_high_y,
-                        WIND_DIRECTION_COLOUR,
-                    ),
-                ] {
-                    graphicimage.line_from_to(
-                        (x, if item.0 >= 6 { item.0 - 6 } else { 0 }),
-                        (x, (item.0 + 6).min(height - 1)),
-                        item.2,
-                    );
-                    graphicimage.line_from_to(
-                        (x, if item.1 >= 6 { item.1 - 6 } else { 0 }),
-                        (x, (item.1 + 6).min(height - 1)),
-                        item.2,
-                    );
-                    graphicimage.line_from_to((x, item.0), (x, item.1), item.2)
+    // Reads a session written by `Data::to_json`, adding its datapoints and counts to these.
+    pub fn load_json(&mut self, reader: Box<dyn BufRead>) -> Result<(), DataError> {
+        let loaded: Data = serde_json::from_reader(reader).map_err(|e| {
+            if e.is_io() {
+                DataError::Io(e.into())
+            } else {
+                DataError::Parse {
+                    line_number: e.line(),
+                    text: String::new(),
+                    reason: e.to_string(),
                 }
+            }
+        })?;
+        self.data.extend(loaded.data);
+        self.normalized_angles += loaded.normalized_angles;
+        self.bad_checksums += loaded.bad_checksums;
+        for (sentence, count) in loaded.unhandled_sentences {
+            *self.unhandled_sentences.entry(sentence).or_default() += count;
+        }
+        self.lines_read += loaded.lines_read;
+        self.skipped_lines.extend(loaded.skipped_lines);
+        self.overview = Overview::new(&self.data, OVERVIEW_WIDTH);
+        Ok(())
+    }
 
-                x += 1;
-                bin_start_time += bin_time_range;
+    // Reads datapoints written by `Data::to_csv`. Derived channels are ignored since they are
+    // recalculated anyway.
+    pub fn load_csv(&mut self, reader: Box<dyn BufRead>) -> Result<(), DataError> {
+        let mut line_number = 0;
+        let mut water_temp_column = None;
+        let mut log_distance_column = None;
+        let mut speed_unit_column = None;
+        for line in reader.lines() {
+            line_number += 1;
+            self.lines_read += 1;
+            let line = match line {
+                Ok(line) => line,
+                Err(e) if e.kind() == ErrorKind::InvalidData => {
+                    self.skip_line(line_number, String::new(), format!("{e:?}"))?;
+                    continue;
+                }
+                Err(e) => return Err(DataError::Io(e)),
+            };
+            if line_number == 1 && line.starts_with(CSV_HEADER) {
+                water_temp_column = line.split(',').position(|a| a == "water_temp");
+                log_distance_column = line.split(',').position(|a| a == "log_distance");
+                speed_unit_column = line.split(',').position(|a| a == "speed_unit");
+                continue;
+            }
+            match parse_csv_line(
+                &line,
+                water_temp_column,
+                log_distance_column,
+                speed_unit_column,
+            ) {
+                Ok(point) => self.data.push(point),
+                Err(reason) => self.skip_line(line_number, line, reason)?,
             }
         }
-        graphicimage.to_image()
+
+        self.overview = Overview::new(&self.data, OVERVIEW_WIDTH);
+        if line_number == 0 {
+            return Err(DataError::Empty);
+        }
+        Ok(())
+    }
+
+    fn skip_line(
+        &mut self,
+        line_number: usize,
+        text: String,
+        reason: String,
+    ) -> Result<(), DataError> {
+        if self.load_options.strict {
+            return Err(DataError::Parse {
+                line_number,
+                text,
+                reason,
+            });
+        }
+        self.skipped_lines.push(SkippedLine {
+            line_number,
+            text,
+            reason,
+        });
+        Ok(())
+    }
+
+    // Instruments reporting relative wind only give true wind once the boat speed is known.
+    // A true reading from the same datapoint takes precedence, and a wind speed from MWD is kept
+    // even when the angle has to come from the relative wind.
+    fn resolve_true_wind(&mut self, dp: &mut DataPoint) {
+        // A wind direction from MWD that arrived before the heading.
+        if let (None, Some(wind_from), Some(heading)) = (dp.winddirection, dp.wind_from, dp.heading)
+        {
+            dp.winddirection = Some((wind_from - heading).rem_euclid(360.));
+        }
+        if dp.winddirection.is_some() || !self.load_options.has_boatspeed(dp) {
+            return;
+        }
+        if let (Some(boatspeed), Some(aws), Some(awa)) = (
+            dp.boatspeed,
+            dp.apparent_windspeed,
+            dp.apparent_winddirection,
+        ) {
+            let (tws, twa) = true_wind(boatspeed, awa, aws);
+            if dp.wind_from.is_none() {
+                dp.windspeed = Some(tws);
+            }
+            dp.winddirection = Some(twa);
+        }
+    }
+
+    // Adds the readings in `dp` as a datapoint at `timestamp`, if there are any, and clears them
+    // for the next one. The time, position and navigation target carry over until they are
+    // updated. When merging sources, each talker's wind since the last datapoint is a datapoint
+    // of its own, with whatever else was read.
+    fn complete_datapoint(
+        &mut self,
+        dp: &mut DataPoint,
+        source_winds: &mut BTreeMap<String, (f32, f32)>,
+        timestamp: DateTime<Utc>,
+    ) {
+        if timestamp == DateTime::<Utc>::default() {
+            return;
+        }
+        if !source_winds.is_empty() {
+            let boatspeed = dp.boatspeed.filter(|_| self.load_options.has_boatspeed(dp));
+            for (source, (windspeed, winddirection)) in std::mem::take(source_winds) {
+                self.data.push(DataPoint {
+                    timestamp,
+                    boatspeed,
+                    windspeed: Some(windspeed),
+                    winddirection: Some(winddirection),
+                    source: Some(source),
+                    ..dp.clone()
+                });
+            }
+        } else if self.load_options.has_reading(dp) {
+            self.data.push(DataPoint {
+                timestamp,
+                ..dp.clone()
+            });
+        } else {
+            return;
+        }
+        *dp = DataPoint {
+            boatspeed: None,
+            windspeed: None,
+            winddirection: None,
+            source: None,
+            wind_from: None,
+            apparent_windspeed: None,
+            apparent_winddirection: None,
+            ..dp.clone()
+        };
     }
 
-    fn process_nmea(&mut self, datapoint: &mut DataPoint, base: Nmea0183Base) {
+    // Moves a true wind out of `dp` to wait under its talker for the datapoint to be completed,
+    // so that one talker's reading doesn't overwrite another's. A wind that counts as missing is
+    // dropped.
+    fn keep_source_wind(
+        &self,
+        dp: &mut DataPoint,
+        source_winds: &mut BTreeMap<String, (f32, f32)>,
+        source: String,
+    ) {
+        let (Some(windspeed), Some(winddirection)) = (dp.windspeed, dp.winddirection) else {
+            return;
+        };
+        if self.load_options.has_wind(dp) {
+            source_winds.insert(source, (windspeed, winddirection));
+        }
+        dp.windspeed = None;
+        dp.winddirection = None;
+        dp.wind_from = None;
+        dp.apparent_windspeed = None;
+        dp.apparent_winddirection = None;
+    }
+
+    pub fn true_wind_direction(&self) -> Vec<(DateTime<Utc>, f32)> {
+        self.data
+            .iter()
+            .filter_map(|a| a.true_wind_direction().map(|b| (a.timestamp, b)))
+            .collect()
+    }
+
+    // Returns false for sentences that were recognised but that nothing is taken from.
+    fn process_nmea(&mut self, datapoint: &mut DataPoint, base: Nmea0183Base) -> bool {
         match classify(base) {
             // These all contain time stamps of one sort or another
             BWC(sentence) => self.process_utc_time(datapoint, sentence.timestamp()),
             BWR(sentence) => self.process_utc_time(datapoint, sentence.timestamp()),
-            GGA(sentence) => self.process_utc_time(datapoint, sentence.timestamp()),
+            GGA(sentence) => {
+                self.process_utc_time(datapoint, sentence.timestamp());
+                self.process_position(datapoint, sentence.latitude(), sentence.longitude());
+            }
+            GLL(sentence) => {
+                self.process_utc_time(datapoint, sentence.timestamp());
+                self.process_position(datapoint, sentence.latitude(), sentence.longitude());
+            }
             GRS(sentence) => self.process_utc_time(datapoint, sentence.timestamp()),
             GST(sentence) => self.process_utc_time(datapoint, sentence.timestamp()),
             GXA(sentence) => self.process_utc_time(datapoint, sentence.timestamp()),
-            RMC(sentence) => self.process_utc_timestamp(datapoint, sentence.timestamp()),
+            RMC(sentence) => {
+                self.process_utc_timestamp(datapoint, sentence.timestamp());
+                self.process_position(datapoint, sentence.latitude(), sentence.longitude());
+                if let (Ok(variation), Ok(direction)) = (
+                    sentence.magnetic_variation(),
+                    sentence.magnetic_variation_direction(),
+                ) {
+                    datapoint.variation = Some(east_positive(variation, direction));
+                }
+            }
             TRF(sentence) => self.process_utc_time(datapoint, sentence.timestamp()),
             ZDA(sentence) => self.process_utc_timestamp(datapoint, sentence.timestamp()),
             ZFO(sentence) => self.process_utc_time(datapoint, sentence.timestamp()),
             ZTG(sentence) => self.process_utc_timestamp(datapoint, sentence.timestamp()),
 
+            // The active waypoint from the autopilot or GPS
+            RMB(sentence) => {
+                if let Ok(bearing) = sentence.bearing_to_destination() {
+                    datapoint.waypoint_bearing = Some(bearing.rem_euclid(360.));
+                }
+                if let Ok(range) = sentence.range_to_destination() {
+                    datapoint.waypoint_range = Some(range);
+                }
+            }
+
+            HDT(sentence) => {
+                if let Ok(heading) = sentence.heading() {
+                    datapoint.heading = Some(heading.rem_euclid(360.));
+                }
+            }
+            // The magnetic sensor heading, with the deviation and variation to correct it by
+            // when the compass knows them.
+            HDG(sentence) => {
+                if let (Ok(variation), Ok(direction)) =
+                    (sentence.variation(), sentence.variation_direction())
+                {
+                    datapoint.variation = Some(east_positive(variation, direction));
+                }
+                let deviation = match (sentence.deviation(), sentence.deviation_direction()) {
+                    (Ok(deviation), Ok(direction)) => east_positive(deviation, direction),
+                    _ => 0.,
+                };
+                if let Ok(heading) = sentence.heading() {
+                    if let Some(heading) = to_true(heading + deviation, datapoint.variation) {
+                        datapoint.heading = Some(heading);
+                    }
+                }
+            }
+            HDM(sentence) => {
+                if let Ok(heading) = sentence.heading() {
+                    if let Some(heading) = to_true(heading, datapoint.variation) {
+                        datapoint.heading = Some(heading);
+                    }
+                }
+            }
+
+            VTG(sentence) => {
+                if let Ok(speed) = sentence.speed_over_ground() {
+                    datapoint.sog = Some(speed.as_knots());
+                }
+                if let Ok(course) = sentence.course_over_ground_true() {
+                    datapoint.cog = Some(course.rem_euclid(360.));
+                }
+            }
+
+            DBT(sentence) => {
+                if let Ok(metres) = sentence.depth_meters() {
+                    datapoint.depth = Some(metres);
+                } else if let Ok(feet) = sentence.depth_feet() {
+                    datapoint.depth = Some(feet * METRES_PER_FOOT);
+                } else if let Ok(fathoms) = sentence.depth_fathoms() {
+                    datapoint.depth = Some(fathoms * METRES_PER_FATHOM);
+                }
+            }
+            DPT(sentence) => {
+                if let Ok(metres) = sentence.depth() {
+                    datapoint.depth = Some(metres);
+                }
+            }
+
+            // The log's odometer. Only the total is kept, as the trip resets whenever the crew
+            // resets it.
+            VLW(sentence) => {
+                if let Ok(distance) = sentence.total_distance() {
+                    datapoint.log_distance = Some(distance);
+                }
+            }
+
+            MTW(sentence) => {
+                if let Ok(temperature) = sentence.temperature() {
+                    datapoint.water_temp = Some(match sentence.unit() {
+                        Ok('F') => (temperature - 32.) * 5. / 9.,
+                        _ => temperature,
+                    });
+                }
+            }
+
             // These contain wind or boat information
             MWV(sentence) => {
+                if let Ok('R') = sentence.reference() {
+                    if let Ok(speed) = sentence.wind_speed() {
+                        datapoint.apparent_windspeed = Some(speed.as_knots());
+                    }
+                    if let Ok(direction) = sentence.angle_true() {
+                        datapoint.apparent_winddirection = Some(self.normalize_angle(direction));
+                    }
+                } else {
+                    // Anything MWD has already given this datapoint is kept.
+                    let from_mwd = datapoint.wind_from.is_some();
+                    if let (false, Ok(speed)) = (from_mwd, sentence.wind_speed()) {
+                        datapoint.windspeed = Some(speed.as_knots());
+                    }
+                    if let Ok(direction) = sentence.angle_true() {
+                        if !from_mwd || datapoint.winddirection.is_none() {
+                            datapoint.winddirection = Some(self.normalize_angle(direction));
+                        }
+                    }
+                }
+            }
+            // Relative wind from older instruments, as an angle off the bow to port or starboard.
+            VWR(sentence) => {
                 if let Ok(speed) = sentence.wind_speed() {
-                    datapoint.windspeed = speed.as_knots();
+                    datapoint.apparent_windspeed = Some(speed.as_knots());
+                }
+                if let (Ok(angle), Ok(side)) = (sentence.wind_angle(), sentence.left_right()) {
+                    let angle = if side == 'L' { 360. - angle } else { angle };
+                    datapoint.apparent_winddirection = Some(self.normalize_angle(angle));
+                }
+            }
+            // True wind as a compass direction, which becomes an angle off the bow once the
+            // heading is known.
+            MWD(sentence) => {
+                let direction = sentence.direction_true().ok().or_else(|| {
+                    sentence
+                        .direction_magnetic()
+                        .ok()
+                        .and_then(|a| to_true(a, datapoint.variation))
+                });
+                if let Some(direction) = direction {
+                    let direction = self.normalize_angle(direction);
+                    datapoint.wind_from = Some(direction);
+                    if let Some(heading) = datapoint.heading {
+                        datapoint.winddirection = Some((direction - heading).rem_euclid(360.));
+                    }
                 }
-                if let Ok(direction) = sentence.angle_true() {
-                    datapoint.winddirection = direction;
+                if let Ok(speed) = sentence.wind_speed() {
+                    datapoint.windspeed = Some(speed.as_knots());
                 }
             }
             VBW(sentence) => {
                 if let Ok(speed) = sentence.water_speed() {
-                    datapoint.boatspeed = speed.as_knots();
+                    datapoint.boatspeed = Some(speed.as_knots());
                 }
             }
             VHW(sentence) => {
                 if let Ok(speed) = sentence.water_speed() {
-                    datapoint.boatspeed = speed.as_knots();
+                    datapoint.boatspeed = Some(speed.as_knots());
                 }
             }
 
-            _ => {}
+            _ => return false,
         }
+        true
+    }
+
+    // Some instruments round wind angles to just outside 0-360, which everything downstream
+    // assumes, so wrap them back into range.
+    fn normalize_angle(&mut self, angle: f32) -> f32 {
+        let normalized = angle.rem_euclid(360.);
+        if normalized != angle {
+            self.normalized_angles += 1;
+        }
+        normalized
     }
 
     fn process_utc_time(&mut self, datapoint: &mut DataPoint, time: DateTimeError) {
         if let Ok(t) = time {
-            let d = datapoint.timestamp.date_naive();
+            let previous = datapoint.timestamp;
+            let mut d = self.last_date.unwrap_or(previous.date_naive());
             let t = t.time();
+            // A time of day well before the previous one means midnight has passed since the
+            // date was last reported.
+            if previous != DateTime::<Utc>::default()
+                && NaiveDateTime::new(d, t) < previous.naive_utc() - TimeDelta::hours(12)
+            {
+                d = d.succ_opt().unwrap_or(d);
+                self.last_date = Some(d);
+            }
             let dt = NaiveDateTime::new(d, t);
             datapoint.timestamp = DateTime::from_naive_utc_and_offset(dt, Utc);
         }
     }
 
+    fn process_position<E>(
+        &mut self,
+        datapoint: &mut DataPoint,
+        latitude: Result<f64, E>,
+        longitude: Result<f64, E>,
+    ) {
+        // Empty position fields mean the receiver has lost its fix, so stop carrying the last
+        // known position forward rather than plotting stale or zeroed coordinates.
+        match (latitude, longitude) {
+            (Ok(latitude), Ok(longitude)) => {
+                datapoint.latitude = Some(latitude);
+                datapoint.longitude = Some(longitude);
+            }
+            _ => {
+                datapoint.latitude = None;
+                datapoint.longitude = None;
+            }
+        }
+    }
+
     fn process_utc_timestamp(&mut self, datapoint: &mut DataPoint, time: DateTimeError) {
         if let Ok(t) = time {
             datapoint.timestamp = t;
+            self.last_date = Some(t.date_naive());
         }
     }
 }
 
-fn calculate_bin_values(data: &Vec<f32>) -> (f32, f32) {
-    if data.len() == 0 {
-        return (0., 0.);
-    } else if data.len() == 1 {
-        return (data[0], data[0]);
+// A variation or deviation as a signed angle, with west negative.
+fn east_positive(angle: f32, direction: char) -> f32 {
+    if direction == 'W' {
+        -angle
+    } else {
+        angle
+    }
+}
+
+// A magnetic bearing converted to true, if the variation is known.
+fn to_true(magnetic: f32, variation: Option<f32>) -> Option<f32> {
+    variation.map(|variation| (magnetic + variation).rem_euclid(360.))
+}
+
+// True wind speed and angle from the boat speed and the apparent wind angle and speed, by
+// taking the boat's own motion away from the apparent wind vector.
+fn true_wind(boatspeed: f32, awa: f32, aws: f32) -> (f32, f32) {
+    if boatspeed == 0. {
+        return (aws, awa);
+    }
+    let awa_radians = awa.to_radians();
+    let along = aws * awa_radians.cos() - boatspeed;
+    let across = aws * awa_radians.sin();
+    let tws = along.hypot(across);
+    let twa = across.atan2(along).to_degrees().rem_euclid(360.);
+    (tws, twa)
+}
+
+// Speeds written in another unit are converted back to knots. Files without a unit column are
+// in knots.
+fn parse_csv_line(
+    line: &str,
+    water_temp_column: Option<usize>,
+    log_distance_column: Option<usize>,
+    speed_unit_column: Option<usize>,
+) -> Result<DataPoint, String> {
+    let fields: Vec<&str> = line.split(',').collect();
+    if fields.len() < 4 {
+        return Err(format!("expected 4 fields, found {}", fields.len()));
+    }
+    let timestamp = DateTime::parse_from_rfc3339(fields[0])
+        .map_err(|e| format!("{e:?}"))?
+        .to_utc();
+    let value = |field: &str| -> Result<Option<f32>, String> {
+        if field.is_empty() {
+            Ok(None)
+        } else {
+            field.parse().map(Some).map_err(|e| format!("{e:?}"))
+        }
     };
+    let optional = |column: Option<usize>| -> Result<Option<f32>, String> {
+        match column.and_then(|a| fields.get(a)) {
+            Some(field) => value(field),
+            None => Ok(None),
+        }
+    };
+    let water_temp = optional(water_temp_column)?;
+    let log_distance = optional(log_distance_column)?;
+    let units = match speed_unit_column.and_then(|a| fields.get(a)) {
+        Some(field) => SpeedUnit::from_abbreviation(field)
+            .ok_or_else(|| format!("unknown speed unit {field}"))?,
+        None => SpeedUnit::Knots,
+    };
+    Ok(DataPoint {
+        timestamp,
+        boatspeed: value(fields[1])?.map(|a| units.to_knots(a)),
+        windspeed: value(fields[2])?.map(|a| units.to_knots(a)),
+        winddirection: value(fields[3])?,
+        water_temp,
+        log_distance,
+        ..DataPoint::new()
+    })
+}
 
-    let mut speed_frequencies: Vec<(f32, i64)> = Vec::new();
-    for item in data {
-        let mut found = false;
-        for entry in &mut speed_frequencies {
-            if entry.0.approx_eq(item) {
-                entry.1 = entry.1 + 1;
-                found = true;
-                break;
-            }
+// Indices of readings more than `max_delta` above or below both of the nearest readings either
+// side of them. Missing readings are skipped over.
+fn find_spikes(values: impl Iterator<Item = Option<f32>>, max_delta: f32) -> Vec<usize> {
+    let present: Vec<(usize, f32)> = values
+        .enumerate()
+        .filter_map(|(index, value)| value.map(|a| (index, a)))
+        .collect();
+    present
+        .windows(3)
+        .filter(|a| {
+            let (before, value, after) = (a[0].1, a[1].1, a[2].1);
+            ((value - before).abs() > max_delta && (value - after).abs() > max_delta)
+                && (value - before).signum() == (value - after).signum()
+        })
+        .map(|a| a[1].0)
+        .collect()
+}
+
+// The spikes `find_spikes` gives in the readings of `data` from `from` on, and in the last one
+// before them, which is checked against the one before it.
+fn find_appended_spikes(
+    data: &[DataPoint],
+    from: usize,
+    value: impl Fn(&DataPoint) -> Option<f32>,
+    max_delta: f32,
+) -> Vec<usize> {
+    let present_before = |end: usize| data[..end].iter().rposition(|a| value(a).is_some());
+    let start = match present_before(from) {
+        Some(last) => present_before(last).unwrap_or(last),
+        None => from,
+    };
+    find_spikes(data[start..].iter().map(&value), max_delta)
+        .into_iter()
+        .map(|a| a + start)
+        .collect()
+}
+
+fn is_present(value: Option<f32>, zero_missing: bool) -> bool {
+    value.is_some_and(|a| !(zero_missing && a == 0.))
+}
+
+// Wraps `reader` in a gzip decoder if the data starts with the gzip magic number, so that
+// compressed logs can be read directly from a file or a pipe.
+fn decompressing_reader(reader: impl Read + 'static) -> Box<dyn BufRead> {
+    let mut reader = BufReader::new(reader);
+    match reader.fill_buf() {
+        Ok(buffer) if buffer.starts_with(&GZIP_MAGIC) => {
+            Box::new(BufReader::new(MultiGzDecoder::new(reader)))
         }
-        if !found {
-            speed_frequencies.push((*item, 1));
+        _ => Box::new(reader),
+    }
+}
+
+// The two character talker ID of a sentence such as "$WIMWV,..."
+fn talker_id(line: &str) -> String {
+    line.trim_start_matches(['$', '!'])
+        .get(0..2)
+        .unwrap_or_default()
+        .to_string()
+}
+
+// Why a sentence's `*hh` checksum, the XOR of everything between the `$` or `!` and the `*`,
+// doesn't match, or None if it does or there isn't one.
+fn checksum_error(line: &str) -> Option<String> {
+    let line = line.trim_end();
+    let body = line.strip_prefix(['$', '!'])?;
+    let (body, checksum) = body.rsplit_once('*')?;
+    let expected = body.bytes().fold(0u8, |a, b| a ^ b);
+    match u8::from_str_radix(checksum, 16) {
+        Ok(actual) if checksum.len() == 2 && actual == expected => None,
+        Ok(_) if checksum.len() == 2 => {
+            Some(format!("bad checksum {checksum}, expected {expected:02X}"))
         }
+        _ => Some(format!("unreadable checksum {checksum:?}")),
     }
+}
 
-    speed_frequencies.sort_by(|a, b| {
-        let compare = a.1.partial_cmp(&b.1).unwrap();
-        if compare == Ordering::Equal {
-            a.0.partial_cmp(&b.0).unwrap()
-        } else {
-            compare
+// Takes heel, trim and barometric pressure from the transducer measurements in an XDR
+// sentence, each a group of type, value, unit and name. Returns false if none of them were
+// recognised.
+fn process_xdr(datapoint: &mut DataPoint, line: &str) -> bool {
+    let body = line.split('*').next().unwrap_or_default();
+    let fields: Vec<&str> = body.split(',').skip(1).collect();
+    let mut used = false;
+    for group in fields.chunks_exact(4) {
+        let Ok(value) = group[1].trim().parse::<f32>() else {
+            continue;
+        };
+        let name = group[3].trim().to_ascii_uppercase();
+        match (group[0].trim(), group[2].trim()) {
+            ("A", "D") if matches!(name.as_str(), "ROLL" | "HEEL") => {
+                datapoint.heel = Some(value);
+            }
+            ("A", "D") if matches!(name.as_str(), "PITCH" | "PTCH" | "TRIM") => {
+                datapoint.trim = Some(value);
+            }
+            ("P", unit) if name.starts_with("BARO") || name.contains("PRESS") => {
+                datapoint.baro = Some(match unit {
+                    "B" => value * 1000.,
+                    "P" => value / 100.,
+                    _ => continue,
+                });
+            }
+            _ => continue,
         }
-    });
-    speed_frequencies.reverse();
-    let a = speed_frequencies[0].0;
-    let b = if speed_frequencies.len() == 1 {
-        speed_frequencies[0].0
-    } else {
-        speed_frequencies[1].0
-    };
-    (a.min(b), a.max(b))
+        used = true;
+    }
+    used
+}
+
+// The three character sentence type of a sentence such as "$WIMWV,..."
+fn sentence_type(line: &str) -> String {
+    line.trim_start_matches(['$', '!'])
+        .get(2..5)
+        .unwrap_or_default()
+        .to_string()
 }
@@ -0,0 +1,201 @@
+use crate::canvas::{Rgb8Pixel, SvgCanvas};
+use crate::datapoints::{Data, CSV_HEADER};
+use crate::graph::GraphOptions;
+use crate::units::SpeedUnit;
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum ExportError {
+    Io(std::io::Error),
+    Image(image::ImageError),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExportError::Io(e) => write!(f, "{e}"),
+            ExportError::Image(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+impl From<std::io::Error> for ExportError {
+    fn from(e: std::io::Error) -> Self {
+        ExportError::Io(e)
+    }
+}
+
+impl From<image::ImageError> for ExportError {
+    fn from(e: image::ImageError) -> Self {
+        ExportError::Image(e)
+    }
+}
+
+// An image's pixels, row by row from the top. Everything is drawn on one of these, whether it's
+// for the window, a PNG or checking what was drawn where.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pixels {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<Rgb8Pixel>,
+}
+
+impl Pixels {
+    // All black, which is the dark theme's background.
+    pub fn new(width: u32, height: u32) -> Pixels {
+        Pixels {
+            width,
+            height,
+            pixels: vec![Rgb8Pixel { r: 0, g: 0, b: 0 }; width as usize * height as usize],
+        }
+    }
+
+    #[cfg(feature = "gui")]
+    pub fn to_image(&self) -> slint::Image {
+        let mut buffer = slint::SharedPixelBuffer::<Rgb8Pixel>::new(self.width, self.height);
+        buffer.make_mut_slice().copy_from_slice(&self.pixels);
+        slint::Image::from_rgb8(buffer)
+    }
+
+    // Tightly packed RGB bytes, as image files want them.
+    pub fn rgb_bytes(&self) -> Vec<u8> {
+        self.pixels.iter().flat_map(|a| [a.r, a.g, a.b]).collect()
+    }
+
+    // The pixel `x` from the left and `y` from the top. The graph is drawn with y counting up
+    // from the bottom, which is `height - 1 - y` here.
+    pub fn get(&self, x: u32, y: u32) -> Option<Rgb8Pixel> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        self.pixels.get((y * self.width + x) as usize).copied()
+    }
+
+    // Whether any pixel in column `x` is exactly `colour`.
+    pub fn column_contains(&self, x: u32, colour: Rgb8Pixel) -> bool {
+        (0..self.height).any(|y| self.get(x, y) == Some(colour))
+    }
+}
+
+pub fn save_png(image: &Pixels, path: &Path) -> Result<(), ExportError> {
+    image::save_buffer(
+        path,
+        &image.rgb_bytes(),
+        image.width,
+        image.height,
+        image::ColorType::Rgb8,
+    )?;
+    Ok(())
+}
+
+impl Data {
+    // The same as `graph`, as an SVG document of lines and text for printing at any size.
+    pub fn graph_svg(
+        &self,
+        width: u32,
+        height: u32,
+        start_datetime: DateTime<Utc>,
+        end_datetime: DateTime<Utc>,
+        options: &GraphOptions,
+    ) -> String {
+        let mut svg = SvgCanvas::new(width, height, options.theme.background());
+        self.draw_graph(
+            &mut svg,
+            None,
+            width,
+            height,
+            start_datetime,
+            end_datetime,
+            options,
+        );
+        svg.finish()
+    }
+
+    // One row per datapoint: the RFC3339 timestamp, boat speed, wind speed, wind angle, water
+    // temperature and the unit the speeds are in, then any derived channels. Missing readings are
+    // left empty.
+    pub fn to_csv(&self, units: SpeedUnit) -> String {
+        let mut csv = String::from(CSV_HEADER);
+        csv += ",water_temp,log_distance,speed_unit";
+        for derived in &self.derived {
+            csv += ",";
+            csv += &derived.name;
+        }
+        csv += "\n";
+        for point in &self.data {
+            csv += &point.timestamp.to_rfc3339();
+            for value in [point.boatspeed, point.windspeed] {
+                csv += &csv_field(value.map(|a| units.from_knots(a)));
+            }
+            csv += &csv_field(point.winddirection);
+            csv += &csv_field(point.water_temp);
+            csv += &csv_field(point.log_distance);
+            csv += ",";
+            csv += units.abbreviation();
+            for derived in &self.derived {
+                csv += &csv_field((derived.function)(point));
+            }
+            csv += "\n";
+        }
+        csv
+    }
+
+    // The whole session as JSON, which `Data::load_reader` recognises and loads without
+    // re-parsing the NMEA it came from.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    // A GPX 1.1 track of every datapoint with a position. Boat speed goes in the Garmin track
+    // point extension, in metres per second, which most viewers can colour the track by.
+    pub fn to_gpx(&self) -> String {
+        let mut gpx = String::from(concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<gpx version=\"1.1\" creator=\"make-polar-rs\"",
+            " xmlns=\"http://www.topografix.com/GPX/1/1\"",
+            " xmlns:gpxtpx=\"http://www.garmin.com/xmlschemas/TrackPointExtension/v2\">\n",
+            "  <trk>\n",
+            "    <trkseg>\n",
+        ));
+        for point in &self.data {
+            let (Some(latitude), Some(longitude)) = (point.latitude, point.longitude) else {
+                continue;
+            };
+            gpx += &format!(
+                concat!(
+                    "      <trkpt lat=\"{:.6}\" lon=\"{:.6}\">\n",
+                    "        <time>{}</time>\n",
+                ),
+                latitude,
+                longitude,
+                point.timestamp.to_rfc3339_opts(SecondsFormat::Millis, true)
+            );
+            if let Some(boatspeed) = point.boatspeed {
+                gpx += &format!(
+                    concat!(
+                        "        <extensions>\n",
+                        "          <gpxtpx:TrackPointExtension>\n",
+                        "            <gpxtpx:speed>{:.3}</gpxtpx:speed>\n",
+                        "          </gpxtpx:TrackPointExtension>\n",
+                        "        </extensions>\n",
+                    ),
+                    SpeedUnit::MetresPerSecond.from_knots(boatspeed)
+                );
+            }
+            gpx += "      </trkpt>\n";
+        }
+        gpx += "    </trkseg>\n  </trk>\n</gpx>\n";
+        gpx
+    }
+}
+
+fn csv_field(value: Option<f32>) -> String {
+    match value {
+        Some(value) => format!(",{value:.2}"),
+        None => ",".to_string(),
+    }
+}
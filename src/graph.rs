@@ -0,0 +1,1207 @@
+use crate::canvas::{Canvas, Rgb8Pixel};
+use crate::channels::{Axis, Channel, Palette, SeriesColours};
+use crate::datapoints::{Data, DataPoint};
+use crate::export::Pixels;
+use crate::gusts::{
+    detect_gusts, GustOrLull, DEFAULT_GUST_SEPARATION_SECONDS, DEFAULT_GUST_THRESHOLD_KNOTS,
+    MARKER_REACH,
+};
+use crate::maneuvers::{detect_maneuvers, MINIMUM_MANEUVER_DWELL_SECONDS};
+use crate::text::{draw_text, text_height, text_width};
+use crate::units::SpeedUnit;
+use chrono::{DateTime, Datelike, TimeDelta, Timelike, Utc};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::f64::consts::PI;
+
+// The sun is considered set once its centre is this far below the horizon, allowing for
+// refraction and the size of its disc.
+const SUNSET_ELEVATION: f64 = -0.833;
+
+// Space around the plot for the time axis below it, the speed axis on the left and the
+// direction axis on the right.
+const TIME_AXIS_HEIGHT: u32 = 16;
+
+pub const SPEED_AXIS_WIDTH: u32 = 24;
+
+const DIRECTION_AXIS_WIDTH: u32 = 30;
+
+// Space between the speed and angle plots in the stacked layout.
+const STACK_GAP: u32 = 8;
+
+// Gridline intervals, in knots, for the speed axis and the closest the gridlines can get.
+const SPEED_TICK_STEPS: [f32; 6] = [1., 2., 5., 10., 20., 50.];
+
+const MINIMUM_GRID_SPACING: u32 = 20;
+
+// Tick intervals, in seconds, that the time axis chooses between, and the closest that ticks
+// are allowed to get so that their labels don't run together.
+const TIME_TICK_INTERVALS: [i64; 15] = [
+    10, 30, 60, 120, 300, 600, 900, 1800, 3600, 7200, 10800, 21600, 43200, 86400, 604800,
+];
+
+const MINIMUM_TICK_SPACING: u32 = 80;
+
+// Port tack wind angles are darkened by one step of this many when folded onto starboard.
+const PORT_SHADE_STEPS: usize = 4;
+
+const BIN_LOW_PERCENTILE: f32 = 10.;
+
+const BIN_HIGH_PERCENTILE: f32 = 90.;
+
+// A comparison log is drawn dimmed to this opacity, and dashed with dashes this many columns
+// long, so that it can't be mistaken for a darker shade of the same series.
+const COMPARISON_OPACITY: f32 = 0.6;
+
+const COMPARISON_DASH: u32 = 4;
+
+// How strongly the band between each bin's low and high shows through over the background, so
+// that the median line drawn on it stands out.
+const ENVELOPE_OPACITY: f32 = 0.35;
+
+const BIN_RESOLUTION: f32 = 0.1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphOptions {
+    // The channels to draw, in drawing order.
+    pub channels: Vec<Channel>,
+    // When set, the speed axis is scaled to this percentile (0-100) of the displayed speeds
+    // instead of the maximum, and anything above it is clamped to the top of the graph.
+    pub scale_percentile: Option<f32>,
+    // Shade the background of night time periods, where position data is available.
+    pub daynight: bool,
+    pub legend: LegendPosition,
+    // Fold wind angles onto 0-180 so both tacks share a scale, rather than plotting 0-360.
+    pub fold_wind_direction: bool,
+    // Plot angles below speeds, each with its own scale, rather than overlaying them.
+    pub stacked: bool,
+    // Width in seconds of a centred moving average drawn over each speed, if any.
+    pub moving_average: Option<u32>,
+    // The unit the speed axis is labelled in.
+    pub units: SpeedUnit,
+    // Mark each tack and gybe with a vertical line.
+    pub maneuvers: bool,
+    // Mark wind speed peaks at least `gust_threshold` knots above the running mean, and troughs
+    // as far below it, keeping only the biggest of each within `gust_separation` seconds.
+    pub gusts: bool,
+    pub gust_threshold: f32,
+    pub gust_separation: u32,
+    pub colours: SeriesColours,
+    pub theme: Theme,
+    // Thickness in pixels of the median and moving average lines.
+    pub line_width: u32,
+    // Blend the edges of those lines into the background rather than stepping them.
+    pub antialias: bool,
+    // Leave a margin at the top for `title`, if any, and `subtitle`, which is the displayed time
+    // range unless given.
+    pub titles: bool,
+    pub title: Option<String>,
+    pub subtitle: Option<String>,
+}
+
+impl GraphOptions {
+    pub fn colour(&self, channel: Channel) -> Rgb8Pixel {
+        match channel {
+            Channel::BoatSpeed => self.colours.boat_speed,
+            Channel::WindSpeed => self.colours.wind_speed,
+            Channel::WindDirection => self.colours.wind_direction,
+            // The other channels' colours are chosen for a dark background.
+            _ if self.theme == Theme::Light => shade(channel.colour(), 1, 1),
+            _ => channel.colour(),
+        }
+    }
+
+    // The axis a channel is drawn against, which for wind angles depends on the folding.
+    pub fn axis(&self, channel: Channel) -> Axis {
+        match channel.axis() {
+            Axis::HalfCircle if !self.fold_wind_direction => Axis::FullCircle,
+            axis => axis,
+        }
+    }
+}
+
+// Which corner of the plot the legend goes in, so that it can be kept off interesting data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LegendPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Hidden,
+}
+
+// Light is for printing, with dark series drawn on white.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl Theme {
+    pub fn background(&self) -> Rgb8Pixel {
+        match self {
+            Theme::Dark => Rgb8Pixel { r: 0, g: 0, b: 0 },
+            Theme::Light => Rgb8Pixel {
+                r: 0xff,
+                g: 0xff,
+                b: 0xff,
+            },
+        }
+    }
+
+    // Axis lines, time labels and the legend border.
+    pub fn axis(&self) -> Rgb8Pixel {
+        match self {
+            Theme::Dark => Rgb8Pixel {
+                r: 0x80,
+                g: 0x80,
+                b: 0x80,
+            },
+            Theme::Light => Rgb8Pixel {
+                r: 0x40,
+                g: 0x40,
+                b: 0x40,
+            },
+        }
+    }
+
+    pub fn grid(&self) -> Rgb8Pixel {
+        match self {
+            Theme::Dark => Rgb8Pixel {
+                r: 0x30,
+                g: 0x30,
+                b: 0x30,
+            },
+            Theme::Light => Rgb8Pixel {
+                r: 0xd8,
+                g: 0xd8,
+                b: 0xd8,
+            },
+        }
+    }
+
+    // Titles, which stand out more than the axis labels.
+    pub fn text(&self) -> Rgb8Pixel {
+        match self {
+            Theme::Dark => Rgb8Pixel {
+                r: 0xe0,
+                g: 0xe0,
+                b: 0xe0,
+            },
+            Theme::Light => Rgb8Pixel {
+                r: 0x20,
+                g: 0x20,
+                b: 0x20,
+            },
+        }
+    }
+
+    pub fn night(&self) -> Rgb8Pixel {
+        match self {
+            Theme::Dark => Rgb8Pixel {
+                r: 0x18,
+                g: 0x18,
+                b: 0x40,
+            },
+            Theme::Light => Rgb8Pixel {
+                r: 0xe0,
+                g: 0xe0,
+                b: 0xf4,
+            },
+        }
+    }
+}
+
+impl Default for GraphOptions {
+    fn default() -> Self {
+        GraphOptions {
+            channels: Channel::DEFAULT.to_vec(),
+            scale_percentile: None,
+            daynight: false,
+            legend: LegendPosition::TopRight,
+            fold_wind_direction: true,
+            stacked: false,
+            moving_average: None,
+            units: SpeedUnit::Knots,
+            maneuvers: true,
+            gusts: false,
+            gust_threshold: DEFAULT_GUST_THRESHOLD_KNOTS,
+            gust_separation: DEFAULT_GUST_SEPARATION_SECONDS,
+            colours: Palette::Default.series_colours(Theme::Dark),
+            theme: Theme::Dark,
+            line_width: 1,
+            antialias: false,
+            titles: false,
+            title: None,
+            subtitle: None,
+        }
+    }
+}
+
+impl Data {
+    pub fn graph(
+        &self,
+        width: u32,
+        height: u32,
+        start_datetime: DateTime<Utc>,
+        end_datetime: DateTime<Utc>,
+        options: &GraphOptions,
+    ) -> Pixels {
+        self.graph_with_comparison(None, width, height, start_datetime, end_datetime, options)
+    }
+
+    // Draws the medians of `comparison`, if any, over the graph on the same axes. It has to
+    // cover the same times to show up, which is what `aligned_to` is for.
+    pub fn graph_with_comparison(
+        &self,
+        comparison: Option<&Data>,
+        width: u32,
+        height: u32,
+        start_datetime: DateTime<Utc>,
+        end_datetime: DateTime<Utc>,
+        options: &GraphOptions,
+    ) -> Pixels {
+        let mut graphicimage = Pixels::new(width, height);
+        if options.theme != Theme::Dark {
+            for y in 0..height {
+                graphicimage.line_from_to(
+                    (0, y),
+                    (width.saturating_sub(1), y),
+                    options.theme.background(),
+                );
+            }
+        }
+        self.draw_graph(
+            &mut graphicimage,
+            comparison,
+            width,
+            height,
+            start_datetime,
+            end_datetime,
+            options,
+        );
+        graphicimage
+    }
+
+    // Draws the graph on `graphicimage`, which is expected to already be filled with the
+    // theme's background.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn draw_graph(
+        &self,
+        graphicimage: &mut impl Canvas,
+        comparison: Option<&Data>,
+        width: u32,
+        height: u32,
+        start_datetime: DateTime<Utc>,
+        end_datetime: DateTime<Utc>,
+        options: &GraphOptions,
+    ) {
+        let theme = options.theme;
+        let title_height = if options.titles {
+            draw_titles(
+                graphicimage,
+                width,
+                height,
+                options.title.as_deref().unwrap_or(""),
+                &options
+                    .subtitle
+                    .clone()
+                    .unwrap_or_else(|| time_range_text(start_datetime, end_datetime)),
+                theme,
+            )
+        } else {
+            0
+        };
+        let area = PlotArea {
+            left: SPEED_AXIS_WIDTH,
+            bottom: TIME_AXIS_HEIGHT,
+            width: width.saturating_sub(SPEED_AXIS_WIDTH + DIRECTION_AXIS_WIDTH),
+            height: height.saturating_sub(TIME_AXIS_HEIGHT + title_height),
+        };
+        // Stacked, the speeds get the upper part of the plot and the angles the lower part, each
+        // scaled to its own height. Otherwise they are overlaid on the whole plot.
+        let (speed_area, angle_area) = if options.stacked {
+            let angle_height = area.height * 2 / 5;
+            (
+                PlotArea {
+                    bottom: area.bottom + angle_height + STACK_GAP,
+                    height: area.height.saturating_sub(angle_height + STACK_GAP),
+                    ..area
+                },
+                PlotArea {
+                    height: angle_height,
+                    ..area
+                },
+            )
+        } else {
+            (area, area)
+        };
+        let area_of = |axis: Axis| -> PlotArea {
+            match axis {
+                Axis::Speed | Axis::Independent | Axis::Depth | Axis::Signed => speed_area,
+                Axis::HalfCircle | Axis::FullCircle => angle_area,
+            }
+        };
+        // Each bin is found by searching forward from the end of the last one, which needs the
+        // window in time order.
+        let window = self.sorted_range(start_datetime, end_datetime);
+        let comparison_window = comparison
+            .map(|a| a.sorted_range(start_datetime, end_datetime))
+            .unwrap_or_default();
+        // Derived channels are registered separately on each data set, so only the others can be
+        // compared.
+        let comparison_channels: Vec<Channel> = options
+            .channels
+            .iter()
+            .copied()
+            .filter(|a| comparison.is_some() && !matches!(a, Channel::Derived(_)))
+            .collect();
+        if window.len() >= 2 && area.width >= 2 && speed_area.height >= 2 && angle_area.height >= 2
+        {
+            let (earliest_time, latest_time) = window
+                .iter()
+                .map(|a| (a.timestamp, a.timestamp))
+                .reduce(|a, b| (a.0.min(b.0), a.1.max(b.1)))
+                .unwrap();
+
+            let mut speeds: Vec<f32> = window
+                .iter()
+                .flat_map(|a| {
+                    options
+                        .channels
+                        .iter()
+                        .filter(|b| b.axis() == Axis::Speed)
+                        .filter_map(|b| self.value(a, *b))
+                })
+                .collect();
+            if let Some(comparison) = comparison {
+                speeds.extend(comparison_window.iter().flat_map(|a| {
+                    comparison_channels
+                        .iter()
+                        .filter(|b| b.axis() == Axis::Speed)
+                        .filter_map(|b| comparison.value(a, *b))
+                }));
+            }
+            let largest_speed = match options.scale_percentile {
+                Some(scale_percentile) => percentile(&mut speeds, scale_percentile),
+                None => speeds.iter().copied().fold(0f32, f32::max),
+            };
+            let speed_ratio = (speed_area.height - 1) as f32 / (largest_speed.floor() + 1f32);
+            let direction_ratio = angle_area.height as f32 / 180f32;
+            let full_circle_ratio = (angle_area.height - 1) as f32 / 360f32;
+            let independent_ratios: BTreeMap<Channel, f32> = options
+                .channels
+                .iter()
+                .filter(|a| matches!(a.axis(), Axis::Independent | Axis::Depth | Axis::Signed))
+                .map(|a| {
+                    let largest = window
+                        .iter()
+                        .filter_map(|b| self.value(b, *a))
+                        .map(f32::abs)
+                        .fold(0f32, f32::max);
+                    let span = if a.axis() == Axis::Signed {
+                        (speed_area.height - 1) / 2
+                    } else {
+                        speed_area.height - 1
+                    };
+                    (*a, span as f32 / (largest.floor() + 1f32))
+                })
+                .collect();
+            let to_y = |channel: Channel, value: f32| -> u32 {
+                let axis = options.axis(channel);
+                let area = area_of(axis);
+                area.bottom
+                    + match axis {
+                        Axis::Speed => ((value * speed_ratio) as u32).min(area.height - 1),
+                        Axis::HalfCircle => area.height - ((value * direction_ratio) as u32).max(1),
+                        Axis::FullCircle => {
+                            area.height
+                                - 1
+                                - ((value * full_circle_ratio) as u32).min(area.height - 1)
+                        }
+                        Axis::Independent => {
+                            ((value * independent_ratios[&channel]) as u32).min(area.height - 1)
+                        }
+                        Axis::Signed => {
+                            let middle = ((area.height - 1) / 2) as f32;
+                            (middle + value * independent_ratios[&channel])
+                                .clamp(0., (area.height - 1) as f32)
+                                as u32
+                        }
+                        Axis::Depth => {
+                            area.height
+                                - 1
+                                - ((value * independent_ratios[&channel]) as u32)
+                                    .min(area.height - 1)
+                        }
+                    }
+            };
+
+            let time_range_milliseconds =
+                (latest_time.min(end_datetime) - earliest_time).num_milliseconds() as f32;
+            // Less than a millisecond per column, which includes every point sharing one
+            // timestamp, would give empty bins that never advance through the data. Bins are
+            // kept at least a millisecond wide, so such a range is drawn in as few columns as it
+            // spans milliseconds, from the left.
+            let bin_time_range =
+                TimeDelta::milliseconds((time_range_milliseconds / area.width as f32) as i64)
+                    .max(TimeDelta::milliseconds(1));
+            let first_bin_time = earliest_time.max(start_datetime);
+            let mut bin_start_time = first_bin_time;
+            let stop_time = latest_time.min(end_datetime);
+
+            let mut sources: Vec<Option<String>> =
+                window.iter().map(|a| a.source.clone()).collect();
+            sources.sort();
+            sources.dedup();
+
+            if let Some(channel) = options.channels.iter().find(|a| a.axis() == Axis::Speed) {
+                // Ticks fall on round numbers of whichever unit is being shown.
+                let units = options.units;
+                let top_speed = units.from_knots(largest_speed.floor() + 1f32);
+                let most_lines = (speed_area.height / MINIMUM_GRID_SPACING).max(1) as f32;
+                let step = *SPEED_TICK_STEPS
+                    .iter()
+                    .find(|a| top_speed / **a <= most_lines)
+                    .unwrap_or(&SPEED_TICK_STEPS[SPEED_TICK_STEPS.len() - 1]);
+                let ticks: Vec<(u32, String)> = (0..)
+                    .map(|a| a as f32 * step)
+                    .take_while(|a| *a < top_speed)
+                    .map(|a| (to_y(*channel, units.to_knots(a)), a.to_string()))
+                    .collect();
+                draw_value_axis(
+                    graphicimage,
+                    &speed_area,
+                    &ticks,
+                    false,
+                    options.colour(*channel),
+                    theme,
+                );
+            }
+            if let Some(channel) = options
+                .channels
+                .iter()
+                .find(|a| matches!(options.axis(**a), Axis::HalfCircle | Axis::FullCircle))
+            {
+                let (top, step) = if options.axis(*channel) == Axis::HalfCircle {
+                    (180, 30)
+                } else {
+                    (360, 90)
+                };
+                let ticks: Vec<(u32, String)> = (0..=top)
+                    .step_by(step)
+                    .map(|a| (to_y(*channel, a as f32), a.to_string()))
+                    .collect();
+                draw_value_axis(
+                    graphicimage,
+                    &angle_area,
+                    &ticks,
+                    true,
+                    options.colour(*channel),
+                    theme,
+                );
+            }
+
+            // Running totals of each speed over the window, so that the moving average around
+            // any time is a difference of two entries.
+            let trends: Vec<(Channel, Vec<(f64, usize)>)> = match options.moving_average {
+                Some(_) => options
+                    .channels
+                    .iter()
+                    .filter(|a| a.axis() == Axis::Speed)
+                    .map(|a| (*a, running_totals(&window, |b| self.value(b, *a))))
+                    .collect(),
+                None => Vec::new(),
+            };
+            let half_average =
+                TimeDelta::milliseconds((options.moving_average.unwrap_or(0) as f64 * 500.) as i64);
+            let mut previous_trends: Vec<Option<u32>> = vec![None; trends.len()];
+
+            let maneuvers = if options.maneuvers {
+                detect_maneuvers(
+                    window.iter().copied(),
+                    TimeDelta::seconds(MINIMUM_MANEUVER_DWELL_SECONDS),
+                )
+            } else {
+                Vec::new()
+            };
+            let mut next_maneuver = 0;
+
+            let gusts = if options.gusts {
+                detect_gusts(
+                    window.iter().copied(),
+                    options.gust_threshold,
+                    TimeDelta::seconds(options.gust_separation as i64),
+                )
+            } else {
+                Vec::new()
+            };
+            let mut next_gust = 0;
+            // The markers go on after everything else so that later columns don't cover them.
+            let mut gust_markers: Vec<(u32, u32, GustOrLull)> = Vec::new();
+
+            // Where each series' median was drawn in the previous column, keyed by channel,
+            // source and tack, so that consecutive medians are joined up.
+            let mut previous_medians: HashMap<(Channel, usize, Option<bool>), u32> = HashMap::new();
+            let mut previous_comparison: HashMap<Channel, u32> = HashMap::new();
+            let mut comparison_cursor = 0;
+
+            let mut x = area.left;
+            let mut cursor = 0;
+
+            while bin_start_time <= stop_time && x <= area.right() {
+                let bin_end_time = bin_start_time + bin_time_range;
+                let bin_start =
+                    cursor + window[cursor..].partition_point(|a| a.timestamp < bin_start_time);
+                let bin_end =
+                    bin_start + window[bin_start..].partition_point(|a| a.timestamp < bin_end_time);
+                let bin_data_set = &window[bin_start..bin_end];
+                cursor = bin_end;
+
+                let mut items = Vec::new();
+                for channel in &options.channels {
+                    // Wind is drawn once per source so that separate instruments can be
+                    // compared.
+                    let groups: Vec<Option<&Option<String>>> = if channel.per_source() {
+                        sources.iter().map(Some).collect()
+                    } else {
+                        vec![None]
+                    };
+                    // Folded angles are drawn separately for each tack, with port a shade darker,
+                    // so that folding doesn't hide which side the wind is on.
+                    let tacks: &[Option<bool>] = if options.axis(*channel) == Axis::HalfCircle {
+                        &[Some(false), Some(true)]
+                    } else {
+                        &[None]
+                    };
+                    for (index, group) in groups.iter().enumerate() {
+                        for tack in tacks {
+                            let bin_values: Vec<f32> = bin_data_set
+                                .iter()
+                                .filter(|a| group.is_none_or(|source| &a.source == source))
+                                .filter_map(|a| self.value(a, *channel))
+                                .filter(|a| tack.is_none_or(|port| (*a > 180f32) == port))
+                                .map(|a| {
+                                    if a > 180f32 && tack.is_some() {
+                                        360f32 - a
+                                    } else {
+                                        a
+                                    }
+                                })
+                                .collect();
+                            if bin_values.is_empty() {
+                                continue;
+                            }
+                            let (bin_low, bin_median, bin_high) = calculate_bin_values(&bin_values);
+                            let colour = shade(options.colour(channel), index, groups.len());
+                            items.push((
+                                (*channel, index, *tack),
+                                to_y(*channel, bin_low),
+                                to_y(*channel, bin_median),
+                                to_y(*channel, bin_high),
+                                if *tack == Some(true) {
+                                    shade(colour, 1, PORT_SHADE_STEPS)
+                                } else {
+                                    colour
+                                },
+                            ));
+                        }
+                    }
+                }
+
+                if options.daynight {
+                    if let Some(position) = bin_data_set
+                        .iter()
+                        .find_map(|a| a.latitude.zip(a.longitude))
+                    {
+                        if sun_elevation(bin_start_time, position.0, position.1) < SUNSET_ELEVATION
+                        {
+                            graphicimage.line_from_to(
+                                (x, area.bottom),
+                                (x, area.top()),
+                                theme.night(),
+                            );
+                        }
+                    }
+                }
+
+                while maneuvers
+                    .get(next_maneuver)
+                    .is_some_and(|a| a.timestamp < bin_end_time)
+                {
+                    let colour = maneuvers[next_maneuver].kind.colour();
+                    graphicimage.line_from_to((x, area.bottom), (x, area.top()), colour);
+                    next_maneuver += 1;
+                }
+
+                // Every band goes down before any median so that no series' median is hidden
+                // under another's band.
+                for (_, low, _, high, colour) in &items {
+                    let fill = blend(*colour, theme.background(), ENVELOPE_OPACITY);
+                    graphicimage.line_from_to((x, *low), (x, *high), fill);
+                }
+                let mut medians = HashMap::new();
+                for (series, _, median, _, colour) in &items {
+                    let previous = previous_medians.get(series).copied().unwrap_or(*median);
+                    draw_trace(
+                        graphicimage,
+                        &area_of(options.axis(series.0)),
+                        (x - 1, previous),
+                        (x, *median),
+                        *colour,
+                        options,
+                    );
+                    medians.insert(*series, *median);
+                }
+                previous_medians = medians;
+
+                while gusts.get(next_gust).is_some_and(|a| a.0 < bin_end_time) {
+                    // On the first source's wind speed, or skipped when that isn't drawn.
+                    if let Some((_, _, median, _, _)) = items
+                        .iter()
+                        .find(|a| a.0 .0 == Channel::WindSpeed && a.0 .1 == 0)
+                    {
+                        gust_markers.push((x, *median, gusts[next_gust].1));
+                    }
+                    next_gust += 1;
+                }
+
+                if let Some(comparison) = comparison {
+                    let bin_start = comparison_cursor
+                        + comparison_window[comparison_cursor..]
+                            .partition_point(|a| a.timestamp < bin_start_time);
+                    let bin_end = bin_start
+                        + comparison_window[bin_start..]
+                            .partition_point(|a| a.timestamp < bin_end_time);
+                    let bin_data_set = &comparison_window[bin_start..bin_end];
+                    comparison_cursor = bin_end;
+                    let mut medians = HashMap::new();
+                    for channel in &comparison_channels {
+                        // Both tacks are folded together, with no shading to tell them apart.
+                        let folded = options.axis(*channel) == Axis::HalfCircle;
+                        let bin_values: Vec<f32> = bin_data_set
+                            .iter()
+                            .filter_map(|a| comparison.value(a, *channel))
+                            .map(|a| if folded && a > 180. { 360. - a } else { a })
+                            .collect();
+                        if bin_values.is_empty() {
+                            continue;
+                        }
+                        let y = to_y(*channel, calculate_bin_values(&bin_values).1);
+                        if (x / COMPARISON_DASH) % 2 == 0 {
+                            let previous = previous_comparison.get(channel).copied().unwrap_or(y);
+                            draw_trace(
+                                graphicimage,
+                                &area_of(options.axis(*channel)),
+                                (x - 1, previous),
+                                (x, y),
+                                blend(
+                                    options.colour(*channel),
+                                    theme.background(),
+                                    COMPARISON_OPACITY,
+                                ),
+                                options,
+                            );
+                        }
+                        medians.insert(*channel, y);
+                    }
+                    previous_comparison = medians;
+                }
+
+                // The window shrinks at the ends of the data rather than leaving the trend out.
+                let centre_time = bin_start_time + bin_time_range / 2;
+                let low = window.partition_point(|a| a.timestamp < centre_time - half_average);
+                let high = window.partition_point(|a| a.timestamp <= centre_time + half_average);
+                for ((channel, totals), previous) in trends.iter().zip(&mut previous_trends) {
+                    let count = totals[high].1 - totals[low].1;
+                    if count == 0 {
+                        *previous = None;
+                        continue;
+                    }
+                    let average = (totals[high].0 - totals[low].0) / count as f64;
+                    let y = to_y(*channel, average as f32);
+                    let colour = shade(options.colour(channel), 1, 2);
+                    draw_trace(
+                        graphicimage,
+                        &speed_area,
+                        (x - 1, previous.unwrap_or(y)),
+                        (x, y),
+                        colour,
+                        options,
+                    );
+                    *previous = Some(y);
+                }
+
+                x += 1;
+                bin_start_time += bin_time_range;
+            }
+
+            for (x, y, kind) in gust_markers {
+                let y = y.clamp(
+                    area.bottom + MARKER_REACH,
+                    area.top()
+                        .saturating_sub(MARKER_REACH)
+                        .max(area.bottom + MARKER_REACH),
+                );
+                kind.draw_marker(graphicimage, x, y);
+            }
+
+            let mut legend: Vec<(String, Rgb8Pixel)> = options
+                .channels
+                .iter()
+                .map(|a| (self.channel_name(*a), options.colour(*a)))
+                .collect();
+            legend.extend(comparison_channels.iter().map(|a| {
+                (
+                    format!("{} (compare)", a.name()),
+                    blend(options.colour(*a), theme.background(), COMPARISON_OPACITY),
+                )
+            }));
+            draw_legend(graphicimage, &area, &legend, options.legend, theme);
+
+            draw_time_axis(
+                graphicimage,
+                &area,
+                first_bin_time,
+                stop_time,
+                bin_time_range,
+                theme,
+            );
+        } else {
+            // Too little to draw a trace from, which is usually the wrong file or an empty
+            // stretch of time, so say so rather than leaving the image blank.
+            let message = "NO DATA";
+            let scale = 2;
+            if text_width(message, scale) < width && text_height(scale) < height {
+                draw_text(
+                    graphicimage,
+                    (width - text_width(message, scale)) / 2,
+                    (height - text_height(scale)) / 2,
+                    message,
+                    scale,
+                    theme.axis(),
+                );
+            }
+        }
+    }
+}
+
+// The part of the graph image that the data is drawn in, leaving room for the axes.
+#[derive(Debug, Clone, Copy)]
+struct PlotArea {
+    left: u32,
+    bottom: u32,
+    width: u32,
+    height: u32,
+}
+
+impl PlotArea {
+    fn right(&self) -> u32 {
+        self.left + self.width - 1
+    }
+
+    fn top(&self) -> u32 {
+        self.bottom + self.height - 1
+    }
+}
+
+// How many pixels across a graph `width` wide are taken up by the data rather than the axes.
+pub fn graph_plot_width(width: u32) -> u32 {
+    width
+        .saturating_sub(SPEED_AXIS_WIDTH + DIRECTION_AXIS_WIDTH)
+        .max(1)
+}
+
+// The time under x pixels across a graph `width` wide showing `start` to `end`, clamped to that
+// range when x is over one of the axes.
+pub fn graph_time_at(
+    width: u32,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    x: f32,
+) -> DateTime<Utc> {
+    let fraction = ((x - SPEED_AXIS_WIDTH as f32) / graph_plot_width(width) as f32).clamp(0., 1.);
+    start
+        + TimeDelta::milliseconds(
+            ((end - start).num_milliseconds() as f64 * fraction as f64) as i64,
+        )
+}
+
+// Draws the axis along the bottom of the plot, with a labelled tick at each whole interval of
+// time. Bins are `bin_time_range` wide starting from `first` at the left of the plot.
+fn draw_time_axis(
+    graphicimage: &mut impl Canvas,
+    area: &PlotArea,
+    first: DateTime<Utc>,
+    last: DateTime<Utc>,
+    bin_time_range: TimeDelta,
+    theme: Theme,
+) {
+    let baseline = area.bottom - 1;
+    graphicimage.line_from_to(
+        (area.left, baseline),
+        (area.right(), baseline),
+        theme.axis(),
+    );
+
+    let bin_milliseconds = bin_time_range.num_milliseconds().max(1);
+    let span_seconds = (last - first).num_seconds();
+    let most_ticks = (area.width / MINIMUM_TICK_SPACING).max(1) as i64;
+    let interval = *TIME_TICK_INTERVALS
+        .iter()
+        .find(|a| span_seconds / **a < most_ticks)
+        .unwrap_or(&TIME_TICK_INTERVALS[TIME_TICK_INTERVALS.len() - 1]);
+    let format = if interval < 60 {
+        "%H:%M:%S"
+    } else if interval < 86400 {
+        "%H:%M"
+    } else {
+        "%m-%d"
+    };
+
+    let mut tick = first.timestamp().div_euclid(interval) * interval;
+    if tick < first.timestamp() {
+        tick += interval;
+    }
+    while tick <= last.timestamp() {
+        let Some(time) = DateTime::<Utc>::from_timestamp(tick, 0) else {
+            break;
+        };
+        let x = area.left + ((time - first).num_milliseconds() / bin_milliseconds) as u32;
+        if x <= area.right() {
+            graphicimage.line_from_to((x, baseline), (x, baseline.saturating_sub(4)), theme.axis());
+            let label = time.format(format).to_string();
+            let label_width = text_width(&label, 1);
+            if x + label_width / 2 < area.left + area.width + DIRECTION_AXIS_WIDTH {
+                draw_text(
+                    graphicimage,
+                    x.saturating_sub(label_width / 2),
+                    2,
+                    &label,
+                    1,
+                    theme.axis(),
+                );
+            }
+        }
+        tick += interval;
+    }
+}
+
+// Draws a vertical axis beside the plot with a labelled tick at each of `ticks`, which are
+// pairs of y coordinate and label. The speed axis on the left also draws gridlines across the
+// plot, behind the data.
+fn draw_value_axis(
+    graphicimage: &mut impl Canvas,
+    area: &PlotArea,
+    ticks: &[(u32, String)],
+    right: bool,
+    colour: Rgb8Pixel,
+    theme: Theme,
+) {
+    let x = if right {
+        area.right() + 1
+    } else {
+        area.left - 1
+    };
+    graphicimage.line_from_to((x, area.bottom), (x, area.top()), theme.axis());
+    for (y, label) in ticks {
+        let label_y = y.saturating_sub(text_height(1) / 2);
+        if right {
+            graphicimage.line_from_to((x, *y), (x + 3, *y), theme.axis());
+            draw_text(graphicimage, x + 5, label_y, label, 1, colour);
+        } else {
+            if *y > area.bottom {
+                graphicimage.line_from_to((area.left, *y), (area.right(), *y), theme.grid());
+            }
+            graphicimage.line_from_to((x - 3, *y), (x, *y), theme.axis());
+            let label_x = (x - 5).saturating_sub(text_width(label, 1));
+            draw_text(graphicimage, label_x, label_y, label, 1, colour);
+        }
+    }
+}
+
+// Draws `title` and `subtitle` centred at the top of the image, leaving out either that's empty
+// or too wide to fit, and returns the height of the margin they take up.
+fn draw_titles(
+    graphicimage: &mut impl Canvas,
+    width: u32,
+    height: u32,
+    title: &str,
+    subtitle: &str,
+    theme: Theme,
+) -> u32 {
+    const PADDING: u32 = 4;
+    const TITLE_SCALE: u32 = 2;
+    let mut margin = PADDING;
+    for (text, scale) in [(title, TITLE_SCALE), (subtitle, 1)] {
+        let text_width = text_width(text, scale);
+        if text.is_empty() || text_width > width {
+            continue;
+        }
+        margin += text_height(scale);
+        if margin > height {
+            break;
+        }
+        draw_text(
+            graphicimage,
+            (width - text_width) / 2,
+            height - margin,
+            text,
+            scale,
+            theme.text(),
+        );
+        margin += PADDING;
+    }
+    margin
+}
+
+// "start - end" for a subtitle, without repeating the date when both are on the same day.
+fn time_range_text(start: DateTime<Utc>, end: DateTime<Utc>) -> String {
+    let end_format = if start.date_naive() == end.date_naive() {
+        "%H:%M:%S"
+    } else {
+        "%Y-%m-%d %H:%M:%S"
+    };
+    format!(
+        "{} - {}",
+        start.format("%Y-%m-%d %H:%M:%S"),
+        end.format(end_format)
+    )
+}
+
+// Draws a box in one corner of the plot with a colour swatch and name for each entry.
+fn draw_legend(
+    graphicimage: &mut impl Canvas,
+    area: &PlotArea,
+    entries: &[(String, Rgb8Pixel)],
+    position: LegendPosition,
+    theme: Theme,
+) {
+    const PADDING: u32 = 4;
+    const SWATCH_WIDTH: u32 = 10;
+    let row_height = text_height(1) + PADDING;
+    let box_width = PADDING * 3
+        + SWATCH_WIDTH
+        + entries
+            .iter()
+            .map(|a| text_width(&a.0, 1))
+            .max()
+            .unwrap_or(0);
+    let box_height = PADDING + entries.len() as u32 * row_height;
+    if position == LegendPosition::Hidden
+        || entries.is_empty()
+        || box_width + 2 * PADDING > area.width
+        || box_height + 2 * PADDING > area.height
+    {
+        return;
+    }
+
+    let left = match position {
+        LegendPosition::TopLeft | LegendPosition::BottomLeft => area.left + PADDING,
+        _ => area.right() - PADDING - box_width,
+    };
+    let bottom = match position {
+        LegendPosition::BottomLeft | LegendPosition::BottomRight => area.bottom + PADDING,
+        _ => area.top() - PADDING - box_height,
+    };
+    let right = left + box_width;
+    let top = bottom + box_height;
+
+    for y in bottom..=top {
+        graphicimage.line_from_to((left, y), (right, y), theme.background());
+    }
+    graphicimage.line_from_to((left, bottom), (right, bottom), theme.axis());
+    graphicimage.line_from_to((right, bottom), (right, top), theme.axis());
+    graphicimage.line_from_to((right, top), (left, top), theme.axis());
+    graphicimage.line_from_to((left, top), (left, bottom), theme.axis());
+
+    for (index, (name, colour)) in entries.iter().enumerate() {
+        let y = top - (index as u32 + 1) * row_height;
+        let swatch_left = left + PADDING;
+        for swatch_y in y + 1..y + text_height(1) {
+            graphicimage.line_from_to(
+                (swatch_left, swatch_y),
+                (swatch_left + SWATCH_WIDTH, swatch_y),
+                *colour,
+            );
+        }
+        draw_text(
+            graphicimage,
+            swatch_left + SWATCH_WIDTH + PADDING,
+            y,
+            name,
+            1,
+            *colour,
+        );
+    }
+}
+
+// Elevation of the sun above the horizon in degrees, using the NOAA low accuracy equations.
+fn sun_elevation(timestamp: DateTime<Utc>, latitude: f64, longitude: f64) -> f64 {
+    let hour = timestamp.hour() as f64
+        + timestamp.minute() as f64 / 60.
+        + timestamp.second() as f64 / 3600.;
+    let gamma = 2. * PI / 365. * (timestamp.ordinal0() as f64 + (hour - 12.) / 24.);
+    let equation_of_time = 229.18
+        * (0.000075 + 0.001868 * gamma.cos()
+            - 0.032077 * gamma.sin()
+            - 0.014615 * (2. * gamma).cos()
+            - 0.040849 * (2. * gamma).sin());
+    let declination = 0.006918 - 0.399912 * gamma.cos() + 0.070257 * gamma.sin()
+        - 0.006758 * (2. * gamma).cos()
+        + 0.000907 * (2. * gamma).sin()
+        - 0.002697 * (3. * gamma).cos()
+        + 0.00148 * (3. * gamma).sin();
+    let true_solar_minutes = hour * 60. + equation_of_time + 4. * longitude;
+    let hour_angle = (true_solar_minutes / 4. - 180.).to_radians();
+    let latitude = latitude.to_radians();
+    let cos_zenith =
+        latitude.sin() * declination.sin() + latitude.cos() * declination.cos() * hour_angle.cos();
+    90. - cos_zenith.clamp(-1., 1.).acos().to_degrees()
+}
+
+// Draws a series line from `from` to `to`, `options.line_width` thick and kept within `area`.
+// Antialiased, the pixels the line only partly covers are blended with the background in
+// proportion to how much of them it covers.
+fn draw_trace(
+    graphicimage: &mut impl Canvas,
+    area: &PlotArea,
+    from: (u32, u32),
+    to: (u32, u32),
+    colour: Rgb8Pixel,
+    options: &GraphOptions,
+) {
+    if options.line_width <= 1 && !options.antialias {
+        graphicimage.line_from_to(from, to, colour);
+        return;
+    }
+    if graphicimage.trace(from, to, colour, options.line_width) {
+        return;
+    }
+    let half_width = options.line_width.max(1) as f32 / 2.;
+    let reach = half_width.ceil() as u32 + 1;
+    let (from_x, from_y) = (from.0 as f32, from.1 as f32);
+    let (dx, dy) = (to.0 as f32 - from_x, to.1 as f32 - from_y);
+    let length_squared = dx * dx + dy * dy;
+    let left = from.0.min(to.0).saturating_sub(reach).max(area.left);
+    let right = (from.0.max(to.0) + reach).min(area.right());
+    let bottom = from.1.min(to.1).saturating_sub(reach).max(area.bottom);
+    let top = (from.1.max(to.1) + reach).min(area.top());
+    for x in left..=right {
+        for y in bottom..=top {
+            // Distance from the centre of the pixel to the nearest point of the line.
+            let along = if length_squared > 0. {
+                (((x as f32 - from_x) * dx + (y as f32 - from_y) * dy) / length_squared)
+                    .clamp(0., 1.)
+            } else {
+                0.
+            };
+            let distance =
+                (x as f32 - (from_x + along * dx)).hypot(y as f32 - (from_y + along * dy));
+            let coverage = if options.antialias {
+                (half_width + 0.5 - distance).clamp(0., 1.)
+            } else if distance <= half_width {
+                1.
+            } else {
+                0.
+            };
+            if coverage >= 1. {
+                graphicimage.line_from_to((x, y), (x, y), colour);
+            } else if coverage > 0. {
+                let blended = blend(colour, options.theme.background(), coverage);
+                graphicimage.line_from_to((x, y), (x, y), blended);
+            }
+        }
+    }
+}
+
+// Mixes `colour` over `background` as if it were drawn with the given opacity.
+pub(crate) fn blend(colour: Rgb8Pixel, background: Rgb8Pixel, opacity: f32) -> Rgb8Pixel {
+    let mix = |a: u8, b: u8| (a as f32 * opacity + b as f32 * (1. - opacity)) as u8;
+    Rgb8Pixel {
+        r: mix(colour.r, background.r),
+        g: mix(colour.g, background.g),
+        b: mix(colour.b, background.b),
+    }
+}
+
+// Cumulative sums and counts of the present values of one channel, with a leading zero so that
+// entry i covers the first i datapoints.
+fn running_totals<F: Fn(&DataPoint) -> Option<f32>>(
+    window: &[&DataPoint],
+    value: F,
+) -> Vec<(f64, usize)> {
+    let mut totals = Vec::with_capacity(window.len() + 1);
+    totals.push((0f64, 0usize));
+    for point in window {
+        let (sum, count) = totals[totals.len() - 1];
+        totals.push(match value(point) {
+            Some(value) => (sum + value as f64, count + 1),
+            None => (sum, count),
+        });
+    }
+    totals
+}
+
+// Darkens a series colour progressively so that several sources of the same quantity can be
+// told apart.
+fn shade(colour: Rgb8Pixel, index: usize, count: usize) -> Rgb8Pixel {
+    let factor = 1f32 - 0.5 * index as f32 / count.max(1) as f32;
+    Rgb8Pixel {
+        r: (colour.r as f32 * factor) as u8,
+        g: (colour.g as f32 * factor) as u8,
+        b: (colour.b as f32 * factor) as u8,
+    }
+}
+
+// Non-finite values are left out, and sort to either end so that the rest stay together.
+pub(crate) fn percentile(data: &mut [f32], percentile: f32) -> f32 {
+    data.sort_unstable_by(f32::total_cmp);
+    let (Some(first), Some(last)) = (
+        data.iter().position(|a| a.is_finite()),
+        data.iter().rposition(|a| a.is_finite()),
+    ) else {
+        return 0.;
+    };
+    let index = (percentile.clamp(0., 100.) / 100. * (last - first) as f32).round() as usize;
+    data[first + index]
+}
+
+// The low end, median and high end of a bin's envelope, with the ends taken as the 10th and
+// 90th percentiles of its values so that a single spike doesn't stretch the whole band. Values
+// are counted in buckets of `BIN_RESOLUTION` so the result doesn't depend on float noise or the
+// order of the readings. An empty bin gives (0, 0, 0).
+pub fn calculate_bin_values(data: &[f32]) -> (f32, f32, f32) {
+    if data.is_empty() {
+        return (0., 0., 0.);
+    }
+    let mut counts: HashMap<i64, usize> = HashMap::new();
+    for value in data {
+        *counts
+            .entry((value / BIN_RESOLUTION).round() as i64)
+            .or_insert(0) += 1;
+    }
+    let mut buckets: Vec<(i64, usize)> = counts.into_iter().collect();
+    buckets.sort_unstable_by_key(|a| a.0);
+
+    let value_at = |percentile: f32| -> f32 {
+        let rank = (percentile / 100. * (data.len() - 1) as f32).round() as usize;
+        let mut seen = 0;
+        for (key, count) in &buckets {
+            seen += count;
+            if seen > rank {
+                return *key as f32 * BIN_RESOLUTION;
+            }
+        }
+        buckets[buckets.len() - 1].0 as f32 * BIN_RESOLUTION
+    };
+    (
+        value_at(BIN_LOW_PERCENTILE),
+        value_at(50.),
+        value_at(BIN_HIGH_PERCENTILE),
+    )
+}
@@ -0,0 +1,790 @@
+use chrono::{DateTime, NaiveDateTime, TimeDelta, Utc};
+use log::error;
+use make_polar_rs::channels::{Axis, Channel};
+use make_polar_rs::datapoints::{Data, DataPoint};
+use make_polar_rs::graph::{graph_plot_width, graph_time_at, GraphOptions};
+use make_polar_rs::live::Follower;
+use make_polar_rs::options::Options;
+use make_polar_rs::units::SpeedUnit;
+use make_polar_rs::view::{
+    is_following, live_offsets, Datasets, GraphCache, ViewState, MINIMUM_TIME_SPAN_SECONDS,
+};
+use make_polar_rs::{export, overview};
+#[cfg(feature = "drag-and-drop")]
+use slint::winit_030::winit::event::WindowEvent;
+#[cfg(feature = "drag-and-drop")]
+use slint::winit_030::{WinitWindowAccessor, WinitWindowEventResult};
+use slint::{Image, ModelRc, SharedString, Timer, TimerMode, VecModel};
+use std::cell::{Cell, RefCell};
+use std::path::Path;
+use std::rc::Rc;
+use std::time::Duration;
+slint::include_modules!();
+
+// Multiples of the on-screen size offered when saving the view, in the order listed in the UI.
+// The UI starts on 2x, which is 2000x800 for the default graph size.
+const EXPORT_SCALES: [u32; 3] = [1, 2, 4];
+
+// Gaps in the data longer than this separate one sailing session from the next.
+const SESSION_GAP_MINUTES: i64 = 60;
+
+// What the despike checkbox uses when no threshold was given on the command line or in the config.
+const DEFAULT_DESPIKE_KNOTS: f32 = 5.;
+
+// How often a followed log is checked for new lines.
+const FOLLOW_INTERVAL_MILLISECONDS: u64 = 1000;
+
+// How much one step of the mouse wheel or one press of +/- zooms in or out by.
+const ZOOM_STEP: f64 = 1.25;
+
+// The series with a checkbox each to show or hide them.
+const MAIN_CHANNELS: [Channel; 3] = [
+    Channel::BoatSpeed,
+    Channel::WindSpeed,
+    Channel::WindDirection,
+];
+
+// What the Open dialog offers and what can be dropped on the window, besides files with no
+// extension at all.
+const LOG_EXTENSIONS: [&str; 6] = ["txt", "log", "nmea", "csv", "json", "gz"];
+
+// How far one press of an arrow key moves the view, as a fraction of the span shown.
+const PAN_STEP: f64 = 0.25;
+
+type Sessions = Vec<(DateTime<Utc>, DateTime<Utc>)>;
+
+#[allow(clippy::too_many_arguments)]
+pub fn run(
+    data: Data,
+    comparison: Option<Data>,
+    graph_options: GraphOptions,
+    options: &Options,
+    follower: Option<Follower>,
+    width: u32,
+    height: u32,
+    range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    profile: bool,
+) -> Result<(), slint::PlatformError> {
+    let (despike, smoothing) = (options.despike, options.smoothing);
+    let despike_threshold = despike.unwrap_or(DEFAULT_DESPIKE_KNOTS);
+    // Shared by every callback rather than copied into each of them, and replaced when another
+    // log is opened.
+    let data = Rc::new(RefCell::new(Datasets::new(
+        data,
+        despike_threshold,
+        comparison,
+    )));
+    let (data_min_timestamp, data_max_timestamp) = data.borrow().range();
+
+    // The range of the data, which grows while following a log and changes when another log
+    // is opened.
+    let data_min = Rc::new(Cell::new(data_min_timestamp));
+    let data_max = Rc::new(Cell::new(data_max_timestamp));
+    let sessions: Rc<RefCell<Sessions>> = Rc::new(RefCell::new(Vec::new()));
+
+    let (start, end) = range.unwrap_or((data_min_timestamp, data_max_timestamp));
+    let view = Rc::new(RefCell::new(ViewState {
+        start,
+        end,
+        options: graph_options,
+        despike: despike.is_some(),
+    }));
+
+    // The channels the checkboxes switch on and off. Any of them not asked for at startup go at
+    // the end of the drawing order if they are switched on.
+    let mut all_channels = view.borrow().options.channels.clone();
+    for channel in MAIN_CHANNELS {
+        if !all_channels.contains(&channel) {
+            all_channels.push(channel);
+        }
+    }
+
+    let ui = AppWindow::new()?;
+
+    let shown = |channel| view.borrow().options.channels.contains(&channel);
+    ui.set_show_boat_speed(shown(Channel::BoatSpeed));
+    ui.set_show_wind_speed(shown(Channel::WindSpeed));
+    ui.set_show_wind_direction(shown(Channel::WindDirection));
+    let units = view.borrow().options.units;
+    ui.set_units_index(SpeedUnit::ALL.iter().position(|a| *a == units).unwrap_or(0) as i32);
+    ui.set_graph_image_height(height as f32);
+    ui.set_graph_image_width(width as f32);
+    ui.set_despike(despike.is_some());
+    ui.set_comparing(data.borrow().comparison.is_some());
+    show_data_summary(&ui, &data.borrow());
+    let mut startup_profile = data.borrow().raw.profile.clone();
+    let cache = Rc::new(RefCell::new(GraphCache::<Image>::default()));
+    let graph_image = startup_profile.time("render", || {
+        cache
+            .borrow_mut()
+            .render(&view.borrow(), &data.borrow(), width, height, |a| {
+                a.to_image()
+            })
+    });
+    ui.set_graph_image(graph_image);
+    ui.set_displayed_range(SharedString::from(displayed_range_text(start, end)));
+    if profile {
+        startup_profile.report();
+    }
+
+    show_data_range(&ui, data_min_timestamp, data_max_timestamp);
+    *sessions.borrow_mut() = show_sessions(&ui, &data.borrow().raw);
+
+    ui.on_redraw_graph({
+        let ui_weak = ui.as_weak();
+        let data_min = data_min.clone();
+        let data_max = data_max.clone();
+        let data = data.clone();
+        let view = view.clone();
+        let cache = cache.clone();
+        move || {
+            let data_min_timestamp = data_min.get();
+            let data_max_timestamp = data_max.get();
+            let ui = ui_weak.unwrap();
+            let start = parse_time(&ui.get_display_timestamp_min());
+            let end = parse_time(&ui.get_display_timestamp_max());
+            ui.set_start_time_invalid(start.is_err());
+            ui.set_end_time_invalid(end.is_err());
+            let (start, end) = match (start, end) {
+                (Ok(start), Ok(end)) => (
+                    start.clamp(data_min_timestamp, data_max_timestamp),
+                    end.clamp(data_min_timestamp, data_max_timestamp),
+                ),
+                (start, end) => {
+                    let errors: Vec<String> =
+                        [start.err(), end.err()].into_iter().flatten().collect();
+                    show_error(&ui, &errors.join("\n"));
+                    return;
+                }
+            };
+            if end <= start {
+                show_error(&ui, "The end time has to be after the start time");
+                ui.set_end_time_invalid(true);
+                return;
+            }
+
+            // Typed times are shown as they were understood, and the sliders follow them.
+            ui.set_display_timestamp_min(SharedString::from(start.to_rfc3339()));
+            ui.set_display_timestamp_max(SharedString::from(end.to_rfc3339()));
+            ui.set_display_start_scroller_value((start - data_min_timestamp).num_seconds() as f32);
+            ui.set_display_end_scroller_value((end - data_min_timestamp).num_seconds() as f32);
+            ui.set_following(is_following(end, data_max_timestamp));
+            ui.set_displayed_range(SharedString::from(displayed_range_text(start, end)));
+            let mut view = view.borrow_mut();
+            view.start = start;
+            view.end = end;
+            ui.set_graph_image(cache.borrow_mut().render(
+                &view,
+                &data.borrow(),
+                width,
+                height,
+                |a| a.to_image(),
+            ));
+        }
+    });
+
+    // The range asked for on the command line replaces the whole of the data once the fields and
+    // sliders can be moved to it.
+    if range.is_some() {
+        show_range(&ui, data_min_timestamp, start, end);
+    }
+
+    ui.on_scroller_changed({
+        let ui_weak = ui.as_weak();
+        let data_min = data_min.clone();
+        move || {
+            let data_min_timestamp = data_min.get();
+            let ui = ui_weak.unwrap();
+            let start_offset: f32 = ui.get_display_start_scroller_value();
+            let end_offset: f32 = ui.get_display_end_scroller_value();
+            let start_delta = TimeDelta::seconds(start_offset as i64);
+            let end_delta = TimeDelta::seconds(end_offset as i64);
+            let start_time = data_min_timestamp + start_delta;
+            let end_time = data_min_timestamp + end_delta;
+            ui.set_display_timestamp_min(SharedString::from(start_time.to_rfc3339()));
+            ui.set_display_timestamp_max(SharedString::from(end_time.to_rfc3339()));
+
+            // Manually moving away from the latest data pauses following; moving back to the
+            // end resumes it.
+            ui.set_following(is_following(
+                end_offset,
+                ui.get_display_scroller_max_value(),
+            ));
+        }
+    });
+
+    ui.on_go_live({
+        let ui_weak = ui.as_weak();
+        move || {
+            let ui = ui_weak.unwrap();
+            let (start_offset, end_offset) = live_offsets(
+                ui.get_display_start_scroller_value(),
+                ui.get_display_end_scroller_value(),
+                ui.get_display_scroller_max_value(),
+            );
+            ui.set_display_start_scroller_value(start_offset);
+            ui.set_display_end_scroller_value(end_offset);
+            ui.invoke_scroller_changed();
+            ui.invoke_redraw_graph();
+        }
+    });
+
+    ui.on_export_view({
+        let ui_weak = ui.as_weak();
+        let data = data.clone();
+        let view = view.clone();
+        move || {
+            let ui = ui_weak.unwrap();
+            let scale = EXPORT_SCALES
+                .get(ui.get_export_scale_index() as usize)
+                .copied()
+                .unwrap_or(1);
+            if let Some(path) = rfd::FileDialog::new()
+                .add_filter("PNG image", &["png"])
+                .set_file_name("graph.png")
+                .save_file()
+            {
+                let image = view.borrow().export(&data.borrow(), width, height, scale);
+                if let Err(e) = export::save_png(&image, &path) {
+                    show_error(&ui, &format!("{}: {e}", path.display()));
+                }
+            }
+        }
+    });
+
+    ui.on_zoom_graph({
+        let ui_weak = ui.as_weak();
+        let data_min = data_min.clone();
+        let view = view.clone();
+        let data_max = data_max.clone();
+        move |fraction, delta| {
+            let data_min_timestamp = data_min.get();
+            let ui = ui_weak.unwrap();
+            let data_max_timestamp = data_max.get();
+            let (start, end) = {
+                let view = view.borrow();
+                (view.start, view.end)
+            };
+            let centre = graph_time_at(width, start, end, fraction * width as f32);
+            let (start, end) = zoomed(
+                start,
+                end,
+                centre,
+                delta > 0.,
+                data_min_timestamp,
+                data_max_timestamp,
+            );
+            show_range(&ui, data_min_timestamp, start, end);
+        }
+    });
+
+    ui.on_zoom_step({
+        let ui_weak = ui.as_weak();
+        let data_min = data_min.clone();
+        let view = view.clone();
+        let data_max = data_max.clone();
+        move |zoom_in| {
+            let data_min_timestamp = data_min.get();
+            let ui = ui_weak.unwrap();
+            let (start, end) = {
+                let view = view.borrow();
+                (view.start, view.end)
+            };
+            let centre = start + (end - start) / 2;
+            let (start, end) = zoomed(
+                start,
+                end,
+                centre,
+                zoom_in,
+                data_min_timestamp,
+                data_max.get(),
+            );
+            show_range(&ui, data_min_timestamp, start, end);
+        }
+    });
+
+    ui.on_pan_step({
+        let ui_weak = ui.as_weak();
+        let data_min = data_min.clone();
+        let view = view.clone();
+        let data_max = data_max.clone();
+        move |steps| {
+            let data_min_timestamp = data_min.get();
+            let ui = ui_weak.unwrap();
+            let (start, end) = {
+                let view = view.borrow();
+                (view.start, view.end)
+            };
+            let span = (end - start).num_milliseconds() as f64;
+            let offset = TimeDelta::milliseconds((steps as f64 * PAN_STEP * span) as i64)
+                .max(data_min_timestamp - start)
+                .min(data_max.get() - end);
+            show_range(&ui, data_min_timestamp, start + offset, end + offset);
+        }
+    });
+
+    ui.on_show_start({
+        let ui_weak = ui.as_weak();
+        let data_min = data_min.clone();
+        let view = view.clone();
+        move || {
+            let data_min_timestamp = data_min.get();
+            let ui = ui_weak.unwrap();
+            let span = {
+                let view = view.borrow();
+                view.end - view.start
+            };
+            show_range(
+                &ui,
+                data_min_timestamp,
+                data_min_timestamp,
+                data_min_timestamp + span,
+            );
+        }
+    });
+
+    ui.on_show_all({
+        let ui_weak = ui.as_weak();
+        let data_min = data_min.clone();
+        let data_max = data_max.clone();
+        move || {
+            let data_min_timestamp = data_min.get();
+            let ui = ui_weak.unwrap();
+            show_range(&ui, data_min_timestamp, data_min_timestamp, data_max.get());
+        }
+    });
+
+    // The range shown when the current drag started, which the drag is measured from.
+    let pan_origin: Rc<Cell<Option<(DateTime<Utc>, DateTime<Utc>)>>> = Rc::new(Cell::new(None));
+
+    ui.on_pan_started({
+        let view = view.clone();
+        let pan_origin = pan_origin.clone();
+        move || {
+            let view = view.borrow();
+            pan_origin.set(Some((view.start, view.end)));
+        }
+    });
+
+    ui.on_pan_graph({
+        let ui_weak = ui.as_weak();
+        let data_min = data_min.clone();
+        let data_max = data_max.clone();
+        move |fraction| {
+            let data_min_timestamp = data_min.get();
+            let ui = ui_weak.unwrap();
+            let data_max_timestamp = data_max.get();
+            let Some((start, end)) = pan_origin.get() else {
+                return;
+            };
+            // Dragging to the right brings earlier times into view.
+            let pixels = fraction as f64 * width as f64;
+            let span = (end - start).num_milliseconds() as f64;
+            let offset =
+                TimeDelta::milliseconds((-pixels / graph_plot_width(width) as f64 * span) as i64)
+                    .max(data_min_timestamp - start)
+                    .min(data_max_timestamp - end);
+            show_range(&ui, data_min_timestamp, start + offset, end + offset);
+        }
+    });
+
+    ui.on_units_changed({
+        let ui_weak = ui.as_weak();
+        let view = view.clone();
+        move || {
+            let ui = ui_weak.unwrap();
+            if let Some(units) = SpeedUnit::ALL.get(ui.get_units_index() as usize) {
+                view.borrow_mut().options.units = *units;
+            }
+            ui.invoke_redraw_graph();
+        }
+    });
+
+    ui.on_session_selected({
+        let ui_weak = ui.as_weak();
+        let data_min = data_min.clone();
+        let data_max = data_max.clone();
+        let sessions = sessions.clone();
+        move |index| {
+            let data_min_timestamp = data_min.get();
+            let ui = ui_weak.unwrap();
+            let (start, end) = match index {
+                0 => (data_min_timestamp, data_max.get()),
+                _ => match sessions.borrow().get(index as usize - 1) {
+                    Some(session) => *session,
+                    None => return,
+                },
+            };
+            // A session that's a single datapoint still needs some span to show.
+            let end = end.max(start + TimeDelta::seconds(MINIMUM_TIME_SPAN_SECONDS));
+            show_range(&ui, data_min_timestamp, start, end);
+        }
+    });
+
+    ui.on_despike_toggled({
+        let ui_weak = ui.as_weak();
+        let view = view.clone();
+        move || {
+            let ui = ui_weak.unwrap();
+            view.borrow_mut().despike = ui.get_despike();
+            ui.invoke_redraw_graph();
+        }
+    });
+
+    ui.on_series_toggled({
+        let ui_weak = ui.as_weak();
+        let view = view.clone();
+        move || {
+            let ui = ui_weak.unwrap();
+            let checked = [
+                (Channel::BoatSpeed, ui.get_show_boat_speed()),
+                (Channel::WindSpeed, ui.get_show_wind_speed()),
+                (Channel::WindDirection, ui.get_show_wind_direction()),
+            ];
+            view.borrow_mut().options.channels = all_channels
+                .iter()
+                .copied()
+                .filter(|a| !checked.contains(&(*a, false)))
+                .collect();
+            ui.invoke_redraw_graph();
+        }
+    });
+
+    ui.on_hover_graph({
+        let ui_weak = ui.as_weak();
+        let data = data.clone();
+        let view = view.clone();
+        move |fraction| {
+            let ui = ui_weak.unwrap();
+            let view = view.borrow();
+            let time = graph_time_at(width, view.start, view.end, fraction * width as f32);
+            ui.set_cursor_time(SharedString::from(
+                time.format("%Y-%m-%d %H:%M:%S").to_string(),
+            ));
+            let data = data.borrow();
+            let text = view
+                .data(&data)
+                .nearest(time)
+                .map(|a| tooltip_text(a, view.options.units))
+                .unwrap_or_default();
+            ui.set_tooltip_text(SharedString::from(text));
+        }
+    });
+
+    // Kept alive until the window closes, or another log is opened.
+    let follow_timer = Rc::new(Timer::default());
+
+    // Replaces the data with a log chosen with the Open button or dropped on the window.
+    let load_log = {
+        let ui_weak = ui.as_weak();
+        let data = data.clone();
+        let view = view.clone();
+        // A title given on the command line or in the config stays, but one defaulting to the
+        // file name follows the log.
+        let title_is_file_name = options.title.is_none();
+        let data_min = data_min.clone();
+        let data_max = data_max.clone();
+        let sessions = sessions.clone();
+        let follow_timer = follow_timer.clone();
+        Rc::new(move |path: &Path| {
+            let ui = ui_weak.unwrap();
+            if path.is_dir() {
+                show_error(&ui, &format!("{} is a folder, not a log", path.display()));
+                return;
+            }
+            // Files without an extension are often logs straight from a logger, so they're let
+            // through.
+            if let Some(extension) = path.extension() {
+                let extension = extension.to_string_lossy().to_lowercase();
+                if !LOG_EXTENSIONS.contains(&extension.as_str()) {
+                    show_error(&ui, &format!("{} doesn't look like a log", path.display()));
+                    return;
+                }
+            }
+            let load_options = data.borrow().raw.load_options.clone();
+            let mut raw = match Data::load_file(path, load_options) {
+                Ok(raw) => raw,
+                Err(e) => {
+                    show_error(&ui, &format!("{}: {e}", path.display()));
+                    return;
+                }
+            };
+            if let Some(tau) = smoothing {
+                raw = raw.smoothed(tau);
+            }
+            // Whatever was being followed has nothing to do with the new log.
+            follow_timer.stop();
+
+            data.borrow_mut().replace_raw(raw, despike_threshold);
+            let datasets = data.borrow();
+            let (min, max) = datasets.range();
+            data_min.set(min);
+            data_max.set(max);
+            show_data_range(&ui, min, max);
+            *sessions.borrow_mut() = show_sessions(&ui, &datasets.raw);
+            show_data_summary(&ui, &datasets);
+            drop(datasets);
+            if title_is_file_name {
+                view.borrow_mut().options.title =
+                    path.file_name().map(|a| a.to_string_lossy().to_string());
+            }
+            ui.set_error_text(SharedString::new());
+            ui.invoke_redraw_graph();
+        })
+    };
+
+    ui.on_compare_file({
+        let ui_weak = ui.as_weak();
+        let data = data.clone();
+        move || {
+            let ui = ui_weak.unwrap();
+            if data.borrow().comparison.is_some() {
+                data.borrow_mut().set_comparison(None);
+                ui.set_comparing(false);
+                ui.invoke_redraw_graph();
+                return;
+            }
+            let Some(path) = rfd::FileDialog::new()
+                .add_filter("Logs", &LOG_EXTENSIONS)
+                .pick_file()
+            else {
+                return;
+            };
+            let mut datasets = data.borrow_mut();
+            match Data::load_file(&path, datasets.raw.load_options.clone()) {
+                Ok(mut comparison) => {
+                    if let Some(tau) = smoothing {
+                        comparison = comparison.smoothed(tau);
+                    }
+                    datasets.set_comparison(Some(comparison));
+                }
+                Err(e) => {
+                    show_error(&ui, &format!("{}: {e}", path.display()));
+                    return;
+                }
+            }
+            drop(datasets);
+            ui.set_comparing(true);
+            ui.invoke_redraw_graph();
+        }
+    });
+
+    ui.on_open_file({
+        let load_log = load_log.clone();
+        move || {
+            if let Some(path) = rfd::FileDialog::new()
+                .add_filter("Logs", &LOG_EXTENSIONS)
+                .pick_file()
+            {
+                load_log(&path);
+            }
+        }
+    });
+
+    // Slint has no file drops of its own, so they're caught from winit underneath it.
+    #[cfg(feature = "drag-and-drop")]
+    ui.window().on_winit_window_event(move |_, event| {
+        if let WindowEvent::DroppedFile(path) = event {
+            load_log(path);
+        }
+        WinitWindowEventResult::Propagate
+    });
+
+    if let Some(mut follower) = follower {
+        let ui_weak = ui.as_weak();
+        let data = data.clone();
+        let data_min = data_min.clone();
+        follow_timer.start(
+            TimerMode::Repeated,
+            Duration::from_millis(FOLLOW_INTERVAL_MILLISECONDS),
+            move || {
+                let ui = ui_weak.unwrap();
+                let mut datasets = data.borrow_mut();
+                match follower.poll(&mut datasets.raw) {
+                    Ok(0) => return,
+                    Ok(_) => {}
+                    Err(e) => {
+                        show_error(&ui, &e.to_string());
+                        return;
+                    }
+                }
+                datasets.raw_changed(despike_threshold);
+                show_data_summary(&ui, &datasets);
+                if let Some((_, last)) = datasets.raw.time_range() {
+                    let last = last.max(data_max.get());
+                    data_max.set(last);
+                    ui.set_data_maximum_time(SharedString::from(last.to_rfc3339()));
+                    ui.set_display_scroller_max_value((last - data_min.get()).num_seconds() as f32);
+                }
+                drop(datasets);
+                // A view pinned to the latest data moves along with it.
+                if ui.get_following() {
+                    ui.invoke_go_live();
+                }
+            },
+        );
+    }
+
+    ui.run()
+}
+
+// Shows the whole of the data from `min` to `max`, with the scrollers spanning it.
+fn show_data_range(ui: &AppWindow, min: DateTime<Utc>, max: DateTime<Utc>) {
+    ui.set_data_minimum_time(SharedString::from(min.to_rfc3339()));
+    ui.set_data_maximum_time(SharedString::from(max.to_rfc3339()));
+    ui.set_display_timestamp_min(SharedString::from(min.to_rfc3339()));
+    ui.set_display_timestamp_max(SharedString::from(max.to_rfc3339()));
+    let span = (max - min).num_seconds() as f32;
+    ui.set_display_scroller_max_value(span);
+    ui.set_display_start_scroller_value(0f32);
+    ui.set_display_end_scroller_value(span);
+}
+
+// Lists each session in the data to choose from, after "All", and returns their time ranges in
+// the same order.
+fn show_sessions(ui: &AppWindow, data: &Data) -> Sessions {
+    let sessions: Sessions = data
+        .sessions(TimeDelta::minutes(SESSION_GAP_MINUTES))
+        .iter()
+        .filter_map(Data::time_range)
+        .collect();
+    let mut session_names = vec![SharedString::from("All")];
+    session_names.extend(sessions.iter().map(|(start, end)| {
+        SharedString::from(format!(
+            "{} - {}",
+            start.format("%Y-%m-%d %H:%M"),
+            end.format("%H:%M")
+        ))
+    }));
+    ui.set_session_names(ModelRc::new(VecModel::from(session_names)));
+    sessions
+}
+
+// Everything shown about the data outside the graph itself, which changes as data arrives.
+fn show_data_summary(ui: &AppWindow, data: &Datasets) {
+    let names: Vec<String> = data
+        .raw
+        .sources
+        .iter()
+        .map(|a| {
+            Path::new(a)
+                .file_name()
+                .map_or(a.clone(), |b| b.to_string_lossy().to_string())
+        })
+        .collect();
+    ui.set_file_name(SharedString::from(names.join(", ")));
+    ui.set_datapoint_count(data.raw.data.len() as i32);
+    ui.set_spike_count(data.despiked.spikes.len() as i32);
+    ui.set_overview_image(
+        data.raw
+            .overview
+            .image(overview::OVERVIEW_HEIGHT)
+            .to_image(),
+    );
+    let totals = &data.totals;
+    let mut status = format!(
+        "{} tacks, {} gybes, {:.1} NM sailed",
+        totals.tacks, totals.gybes, totals.distance_sailed
+    );
+    if let Some(made_good) = totals.distance_made_good {
+        status += &format!(", {made_good:.1} NM made good");
+    }
+    ui.set_status_text(SharedString::from(status));
+}
+
+// The displayed range for the status bar, leaving out the date at the end if it's the same day.
+fn displayed_range_text(start: DateTime<Utc>, end: DateTime<Utc>) -> String {
+    let end_format = if start.date_naive() == end.date_naive() {
+        "%H:%M:%S"
+    } else {
+        "%Y-%m-%d %H:%M:%S"
+    };
+    format!(
+        "{} to {} ({}m)",
+        start.format("%Y-%m-%d %H:%M:%S"),
+        end.format(end_format),
+        (end - start).num_minutes()
+    )
+}
+
+// One line for the time and one for each of the main channels, with a dash for missing values.
+fn tooltip_text(point: &DataPoint, units: SpeedUnit) -> String {
+    let mut lines = vec![point.timestamp.format("%Y-%m-%d %H:%M:%S").to_string()];
+    for channel in Channel::DEFAULT {
+        let value = match point.get(channel) {
+            Some(value) if channel.axis() == Axis::Speed => {
+                format!("{:.1} {}", units.from_knots(value), units.abbreviation())
+            }
+            Some(value) => format!("{value:.1}"),
+            None => "-".to_string(),
+        };
+        lines.push(format!("{}: {value}", channel.name()));
+    }
+    lines.join("\n")
+}
+
+// Every failure caused by something done in the window is reported here, so that it's seen
+// there as well as on stderr.
+fn show_error(ui: &AppWindow, message: &str) {
+    error!("{message}");
+    ui.set_error_text(SharedString::from(message));
+}
+
+// Reads a time typed into one of the range fields, either as RFC 3339 or as a UTC date and
+// time like "2024-06-01 14:30".
+fn parse_time(text: &str) -> Result<DateTime<Utc>, String> {
+    const FORMATS: [&str; 4] = [
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+    ];
+    let text = text.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Ok(time.to_utc());
+    }
+    FORMATS
+        .iter()
+        .find_map(|a| NaiveDateTime::parse_from_str(text, a).ok())
+        .map(|a| a.and_utc())
+        .ok_or_else(|| format!("{text:?} isn't a time like 2024-06-01 14:30"))
+}
+
+// The range after zooming `start` to `end` one step in or out around `centre`, kept within the
+// data and no shorter than the minimum span.
+fn zoomed(
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    centre: DateTime<Utc>,
+    zoom_in: bool,
+    data_min_timestamp: DateTime<Utc>,
+    data_max_timestamp: DateTime<Utc>,
+) -> (DateTime<Utc>, DateTime<Utc>) {
+    let factor = if zoom_in { 1. / ZOOM_STEP } else { ZOOM_STEP };
+    let scaled =
+        |span: TimeDelta| TimeDelta::milliseconds((span.num_milliseconds() as f64 * factor) as i64);
+    let start = (centre - scaled(centre - start)).max(data_min_timestamp);
+    let end = (centre + scaled(end - centre)).min(data_max_timestamp);
+    if end - start < TimeDelta::seconds(MINIMUM_TIME_SPAN_SECONDS) {
+        (
+            centre - TimeDelta::milliseconds(MINIMUM_TIME_SPAN_SECONDS * 500),
+            centre + TimeDelta::milliseconds(MINIMUM_TIME_SPAN_SECONDS * 500),
+        )
+    } else {
+        (start, end)
+    }
+}
+
+// Moves the sliders and time fields to show `start` to `end` and redraws the graph.
+fn show_range(
+    ui: &AppWindow,
+    data_min_timestamp: DateTime<Utc>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) {
+    ui.set_display_timestamp_min(SharedString::from(start.to_rfc3339()));
+    ui.set_display_timestamp_max(SharedString::from(end.to_rfc3339()));
+    ui.set_display_start_scroller_value((start - data_min_timestamp).num_seconds() as f32);
+    ui.set_display_end_scroller_value((end - data_min_timestamp).num_seconds() as f32);
+    ui.invoke_redraw_graph();
+}
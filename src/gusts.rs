@@ -0,0 +1,131 @@
+use crate::canvas::{Canvas, Rgb8Pixel};
+use crate::datapoints::{Data, DataPoint};
+use chrono::{DateTime, TimeDelta, Utc};
+
+pub const DEFAULT_GUST_THRESHOLD_KNOTS: f32 = 3.;
+pub const DEFAULT_GUST_SEPARATION_SECONDS: u32 = 30;
+// Gusts and lulls are measured against the mean wind speed over this long, centred on them.
+pub const GUST_MEAN_SECONDS: i64 = 120;
+
+// Half the width of the triangles marking gusts and lulls on the graph, and how far they sit
+// from the wind speed trace.
+const MARKER_SIZE: u32 = 3;
+const MARKER_GAP: u32 = 2;
+// How far above or below the trace the markers reach.
+pub(crate) const MARKER_REACH: u32 = MARKER_GAP + 2 * MARKER_SIZE;
+
+const GUST_COLOUR: Rgb8Pixel = Rgb8Pixel {
+    r: 0xff,
+    g: 0x40,
+    b: 0x40,
+};
+const LULL_COLOUR: Rgb8Pixel = Rgb8Pixel {
+    r: 0x40,
+    g: 0x80,
+    b: 0xff,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GustOrLull {
+    Gust,
+    Lull,
+}
+
+impl GustOrLull {
+    pub fn colour(&self) -> Rgb8Pixel {
+        match self {
+            GustOrLull::Gust => GUST_COLOUR,
+            GustOrLull::Lull => LULL_COLOUR,
+        }
+    }
+
+    // A triangle pointing up above the trace at `(x, y)` for a gust, or down below it for a
+    // lull.
+    pub fn draw_marker(&self, graphicimage: &mut impl Canvas, x: u32, y: u32) {
+        let (base, tip) = match self {
+            GustOrLull::Gust => (y + MARKER_GAP, y + MARKER_REACH),
+            GustOrLull::Lull => (y.saturating_sub(MARKER_GAP), y.saturating_sub(MARKER_REACH)),
+        };
+        let (left, right) = (x.saturating_sub(MARKER_SIZE), x + MARKER_SIZE);
+        let colour = self.colour();
+        graphicimage.line_from_to((left, base), (right, base), colour);
+        graphicimage.line_from_to((right, base), (x, tip), colour);
+        graphicimage.line_from_to((x, tip), (left, base), colour);
+    }
+}
+
+// Finds the peaks of wind speed at least `threshold` knots above the mean around them, and the
+// troughs as far below it, in `points`, which need to be in time order. Of several of the same
+// kind closer together than `min_separation`, only the biggest is kept. Each comes with how
+// far it was from the mean.
+pub fn detect_gusts<'a>(
+    points: impl IntoIterator<Item = &'a DataPoint>,
+    threshold: f32,
+    min_separation: TimeDelta,
+) -> Vec<(DateTime<Utc>, GustOrLull, f32)> {
+    let readings: Vec<(DateTime<Utc>, f32)> = points
+        .into_iter()
+        .filter_map(|a| a.windspeed.map(|b| (a.timestamp, b)))
+        .collect();
+    // Running totals, so that the mean over any stretch is a difference of two of them.
+    let mut totals = vec![0f64];
+    for (_, speed) in &readings {
+        totals.push(totals[totals.len() - 1] + *speed as f64);
+    }
+    let half_mean = TimeDelta::seconds(GUST_MEAN_SECONDS / 2);
+    let (mut low, mut high) = (0, 0);
+    let deviations: Vec<f32> = readings
+        .iter()
+        .map(|(timestamp, speed)| {
+            while readings[low].0 < *timestamp - half_mean {
+                low += 1;
+            }
+            while high < readings.len() && readings[high].0 <= *timestamp + half_mean {
+                high += 1;
+            }
+            let mean = (totals[high] - totals[low]) / (high - low) as f64;
+            speed - mean as f32
+        })
+        .collect();
+
+    let mut events: Vec<(DateTime<Utc>, GustOrLull, f32)> = Vec::new();
+    for (index, deviation) in deviations.iter().enumerate() {
+        let before = index.checked_sub(1).map(|a| deviations[a]);
+        let after = deviations.get(index + 1).copied();
+        let kind = if *deviation >= threshold
+            && before.is_none_or(|a| a <= *deviation)
+            && after.is_none_or(|a| a < *deviation)
+        {
+            GustOrLull::Gust
+        } else if *deviation <= -threshold
+            && before.is_none_or(|a| a >= *deviation)
+            && after.is_none_or(|a| a > *deviation)
+        {
+            GustOrLull::Lull
+        } else {
+            continue;
+        };
+        let event = (readings[index].0, kind, deviation.abs());
+        match events.iter_mut().rev().find(|a| a.1 == kind) {
+            Some(last) if event.0 - last.0 < min_separation => {
+                if event.2 > last.2 {
+                    *last = event;
+                }
+            }
+            _ => events.push(event),
+        }
+    }
+    // Keeping the bigger of two gusts can move one past a lull between them.
+    events.sort_by_key(|a| a.0);
+    events
+}
+
+impl Data {
+    pub fn gusts(
+        &self,
+        threshold: f32,
+        min_separation: TimeDelta,
+    ) -> Vec<(DateTime<Utc>, GustOrLull, f32)> {
+        detect_gusts(&self.data, threshold, min_separation)
+    }
+}
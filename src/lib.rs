@@ -0,0 +1,18 @@
+pub mod canvas;
+pub mod channels;
+pub mod current;
+pub mod datapoints;
+pub mod export;
+pub mod graph;
+pub mod gusts;
+pub mod live;
+pub mod maneuvers;
+pub mod options;
+pub mod overview;
+pub mod polar;
+pub mod profile;
+pub mod rose;
+pub mod summary;
+pub mod text;
+pub mod units;
+pub mod view;
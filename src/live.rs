@@ -0,0 +1,160 @@
+use crate::datapoints::{Data, DataError, PendingReadings};
+use log::{info, warn};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Seek, SeekFrom};
+use std::net::TcpStream;
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// How long a read from a serial port waits for data before trying again.
+#[cfg(feature = "serial")]
+const SERIAL_TIMEOUT_MILLISECONDS: u64 = 1000;
+
+// Waits between attempts to reopen a TCP or serial source, doubling from the first up to the last.
+const FIRST_RECONNECT_DELAY_MILLISECONDS: u64 = 500;
+const LONGEST_RECONNECT_DELAY_MILLISECONDS: u64 = 30000;
+
+enum Source {
+    // A log that's still being written to, read from wherever the last poll stopped, like
+    // `tail -f`.
+    File {
+        reader: BufReader<File>,
+        // The start of a line whose newline hasn't been written yet.
+        partial: String,
+    },
+    // Complete lines read on another thread from a connection that blocks until there are more.
+    Lines(Receiver<io::Result<String>>),
+}
+
+// NMEA arriving while the program runs, added to the data a batch at a time whenever it's polled.
+pub struct Follower {
+    source: Source,
+    // Readings since the last datapoint was completed.
+    pending: PendingReadings,
+}
+
+impl Follower {
+    // Starts at the current end of the file, which is expected to have been loaded already.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Follower> {
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::End(0))?;
+        Ok(Follower::new(Source::File {
+            reader: BufReader::new(file),
+            partial: String::new(),
+        }))
+    }
+
+    // Reads lines from a multiplexer or chart plotter serving NMEA over TCP at `address`
+    // (host:port), reconnecting whenever the connection drops.
+    pub fn connect_tcp(address: String) -> Follower {
+        Follower::spawn(address.clone(), move || {
+            TcpStream::connect(&address).map(|a| Box::new(a) as Box<dyn Read + Send>)
+        })
+    }
+
+    // Reads lines from an instrument on a serial port, such as an RS-422 talker on a USB adapter.
+    #[cfg(feature = "serial")]
+    pub fn open_serial(device: String, baud: u32) -> Follower {
+        Follower::spawn(device.clone(), move || {
+            serialport::new(&device, baud)
+                .timeout(Duration::from_millis(SERIAL_TIMEOUT_MILLISECONDS))
+                .open()
+                .map(|a| Box::new(a) as Box<dyn Read + Send>)
+                .map_err(io::Error::from)
+        })
+    }
+
+    // Reads lines on another thread from whatever `connect` opens, opening it again with a
+    // growing delay whenever it fails or closes. Lines can arrive split across several reads,
+    // so anything after the last newline waits for the rest of its line.
+    fn spawn<F>(name: String, connect: F) -> Follower
+    where
+        F: Fn() -> io::Result<Box<dyn Read + Send>> + Send + 'static,
+    {
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            let mut delay = FIRST_RECONNECT_DELAY_MILLISECONDS;
+            let mut buffer = [0u8; 1024];
+            loop {
+                match connect() {
+                    Ok(mut stream) => {
+                        info!("Reading from {name}");
+                        delay = FIRST_RECONNECT_DELAY_MILLISECONDS;
+                        let mut partial: Vec<u8> = Vec::new();
+                        loop {
+                            let read = match stream.read(&mut buffer) {
+                                Ok(0) => break,
+                                Ok(read) => read,
+                                // Serial ports time out whenever the talker is quiet.
+                                Err(e)
+                                    if matches!(
+                                        e.kind(),
+                                        ErrorKind::TimedOut | ErrorKind::Interrupted
+                                    ) =>
+                                {
+                                    continue
+                                }
+                                Err(e) => {
+                                    warn!("{name}: {e}");
+                                    break;
+                                }
+                            };
+                            partial.extend_from_slice(&buffer[..read]);
+                            while let Some(end) = partial.iter().position(|a| *a == b'\n') {
+                                let line: Vec<u8> = partial.drain(..=end).collect();
+                                let line = String::from_utf8(line)
+                                    .map(|a| a.trim_end().to_string())
+                                    .map_err(|e| io::Error::new(ErrorKind::InvalidData, e));
+                                if sender.send(line).is_err() {
+                                    // Nothing is polling any more.
+                                    return;
+                                }
+                            }
+                        }
+                        warn!("{name}: disconnected");
+                    }
+                    Err(e) => warn!("{name}: {e}"),
+                }
+                thread::sleep(Duration::from_millis(delay));
+                delay = (delay * 2).min(LONGEST_RECONNECT_DELAY_MILLISECONDS);
+            }
+        });
+        Follower::new(Source::Lines(receiver))
+    }
+
+    fn new(source: Source) -> Follower {
+        Follower {
+            source,
+            pending: PendingReadings::default(),
+        }
+    }
+
+    // The next complete line, or None if there isn't one yet.
+    fn next_line(&mut self) -> Option<io::Result<String>> {
+        match &mut self.source {
+            Source::File { reader, partial } => match reader.read_line(partial) {
+                Ok(0) => None,
+                Ok(_) if !partial.ends_with('\n') => None,
+                Ok(_) => Some(Ok(std::mem::take(partial).trim_end().to_string())),
+                Err(e) => Some(Err(e)),
+            },
+            Source::Lines(receiver) => match receiver.try_recv() {
+                Ok(line) => Some(line),
+                Err(TryRecvError::Empty | TryRecvError::Disconnected) => None,
+            },
+        }
+    }
+
+    // Reads whatever has been appended since the last poll into `data`, returning how many
+    // datapoints that added. Only those are added to the overview.
+    pub fn poll(&mut self, data: &mut Data) -> Result<usize, DataError> {
+        let before = data.data.len();
+        while let Some(line) = self.next_line() {
+            data.read_line(&mut self.pending, line, Instant::now())?;
+        }
+        data.overview.extend(&data.data, before);
+        Ok(data.data.len() - before)
+    }
+}
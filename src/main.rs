@@ -1,106 +1,667 @@
-mod datapoints;
+#[cfg(feature = "gui")]
+mod gui;
 
-use chrono::{DateTime, TimeDelta};
-use clap::Parser;
-use slint::SharedString;
-slint::include_modules!();
+use chrono::{DateTime, TimeDelta, Utc};
+use clap::{ArgAction, Parser};
+use log::{error, warn, LevelFilter};
 
-use datapoints::Data;
-
-const GRAPH_IMAGE_WIDTH: u32 = 1000;
-const GRAPH_IMAGE_HEIGHT: u32 = 400;
+use make_polar_rs::datapoints::Data;
+use make_polar_rs::export;
+#[cfg(feature = "gui")]
+use make_polar_rs::live::Follower;
+use make_polar_rs::maneuvers::ManeuverKind;
+use make_polar_rs::options::Options;
+use make_polar_rs::polar::{PointOfSail, SailingModes, Target, POLAR_TWA_STEP};
+use make_polar_rs::rose::DEFAULT_WIND_ROSE_SECTORS;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::exit;
+use std::str::FromStr;
 
 #[derive(Debug, Parser)]
 struct Cli {
-    filename: Option<String>,
+    /// NMEA 0183 log files, read from stdin if none are given
+    filenames: Vec<String>,
+
+    /// Read default options from this file instead of ./make-polar.toml or
+    /// ~/.config/make-polar/config.toml
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    #[command(flatten)]
+    options: Options,
+
+    /// Log more about loading and connecting: -v for debugging detail, -vv for everything
+    #[arg(short, long, action = ArgAction::Count, conflicts_with = "quiet")]
+    verbose: u8,
+
+    /// Log less: -q for only warnings and errors, -qq for only errors, -qqq for nothing
+    #[arg(short, long, action = ArgAction::Count)]
+    quiet: u8,
+
+    /// Report sentences that were read but not used, and any data that needed correcting
+    #[arg(long)]
+    inspect: bool,
+
+    /// Report the time spent in each stage of loading and startup
+    #[arg(long)]
+    profile: bool,
+
+    /// Keep reading lines appended to the log file and update the graph as they arrive
+    #[arg(long)]
+    follow: bool,
+
+    /// Read NMEA live from a TCP server at host:port, such as a multiplexer or OpenCPN, as well
+    /// as any log files given
+    #[arg(long)]
+    tcp: Option<String>,
+
+    /// Read NMEA live from this serial device, as well as any log files given
+    #[cfg(feature = "serial")]
+    #[arg(long)]
+    serial: Option<String>,
+
+    /// Baud rate for --serial: 4800 for standard NMEA 0183, 38400 for high speed talkers
+    #[cfg(feature = "serial")]
+    #[arg(long, default_value_t = 4800)]
+    baud: u32,
+
+    /// Another log to overlay, dimmed and dashed, moved to start when the main logs do
+    #[arg(long)]
+    compare: Option<String>,
+
+    /// Print the time range, speeds and time spent in each wind band, without opening a window
+    #[arg(long)]
+    summary: bool,
+
+    /// Only load the data and report on it, without opening a window
+    #[arg(long)]
+    no_gui: bool,
+
+    /// Write the polar as a table of boat speeds by true wind angle and speed, without opening
+    /// a window
+    #[arg(long)]
+    polar_csv: Option<PathBuf>,
+
+    /// Render the polar to this PNG file, with the --compare log's curves drawn over it, without
+    /// opening a window
+    #[arg(long)]
+    polar: Option<PathBuf>,
+
+    /// Split --polar-csv and --polar into separate upwind, reaching and downwind polars, named
+    /// like polar-upwind.csv
+    #[arg(long)]
+    by_point_of_sail: bool,
+
+    /// Write the datapoints to this CSV file, without opening a window
+    #[arg(long)]
+    csv: Option<PathBuf>,
+
+    /// Write the loaded session to this JSON file, which loads faster than the original logs,
+    /// without opening a window
+    #[arg(long)]
+    json: Option<PathBuf>,
+
+    /// Write the logged positions to this GPX file as a track, without opening a window
+    #[arg(long)]
+    gpx: Option<PathBuf>,
+
+    /// Render the graph to this PNG file, without opening a window
+    #[arg(long)]
+    output: Option<PathBuf>,
+
+    /// Render the graph to this SVG file, which prints at any size, without opening a window
+    #[arg(long)]
+    svg: Option<PathBuf>,
+
+    /// Render a wind rose of the time spent with the true wind from each direction to this PNG
+    /// file, without opening a window
+    #[arg(long)]
+    wind_rose: Option<PathBuf>,
+
+    /// Number of compass sectors in the wind rose, usually 16 or 32
+    #[arg(long, default_value_t = DEFAULT_WIND_ROSE_SECTORS,
+          value_parser = clap::value_parser!(u32).range(4..=72).map(|a| a as usize))]
+    wind_rose_sectors: usize,
+
+    /// Render boat speed against true wind angle for the --twa-curve-band true wind speeds to
+    /// this PNG file, without opening a window
+    #[arg(long)]
+    twa_curve: Option<PathBuf>,
+
+    /// True wind speeds for --twa-curve, as the lowest included and the highest excluded knots
+    #[arg(long, default_value = "8,12", value_parser = parse_tws_range)]
+    twa_curve_band: (f32, f32),
+
+    /// Start of the time range to graph, in RFC3339 or like +2h or +1h30m after the first
+    /// datapoint [default: the first datapoint]
+    #[arg(long)]
+    start: Option<TimeArg>,
+
+    /// End of the time range to graph, in RFC3339 or like +2h or +1h30m after the first
+    /// datapoint [default: the last datapoint]
+    #[arg(long)]
+    end: Option<TimeArg>,
+}
+
+// Why the program stopped with an error once it was running. Bad arguments and unreadable files
+// are reported where they're found instead.
+#[derive(Debug)]
+enum AppError {
+    // The window couldn't be opened, such as when there's no display.
+    #[cfg(feature = "gui")]
+    Window(slint::PlatformError),
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            #[cfg(feature = "gui")]
+            AppError::Window(ref e) => write!(f, "couldn't open the window: {e}"),
+        }
+    }
 }
-fn main() -> Result<(), slint::PlatformError> {
+
+impl std::error::Error for AppError {}
+
+#[cfg(feature = "gui")]
+impl From<slint::PlatformError> for AppError {
+    fn from(e: slint::PlatformError) -> Self {
+        AppError::Window(e)
+    }
+}
+
+fn main() -> Result<(), AppError> {
     let cli = Cli::parse();
-    let data = Data::load_filename(cli.filename.clone());
+    // Logging goes to stderr, so that anything written to stdout can be piped elsewhere. RUST_LOG
+    // overrides the level set by -v and -q.
+    env_logger::Builder::new()
+        .filter_level(log_level(cli.verbose, cli.quiet))
+        .format_target(false)
+        .parse_default_env()
+        .init();
+    let options = cli
+        .options
+        .clone()
+        .or(Options::load_config(cli.config.as_deref()));
+    // Headless runs go straight from loading to reporting and never initialise slint's
+    // windowing platform, which isn't even built in without the gui feature.
+    let headless = cli.no_gui
+        || cli.summary
+        || cli.polar_csv.is_some()
+        || cli.polar.is_some()
+        || cli.csv.is_some()
+        || cli.json.is_some()
+        || cli.gpx.is_some()
+        || cli.output.is_some()
+        || cli.svg.is_some()
+        || cli.wind_rose.is_some()
+        || cli.twa_curve.is_some()
+        || cfg!(not(feature = "gui"));
+    #[cfg(feature = "serial")]
+    let serial = cli.serial.clone();
+    #[cfg(not(feature = "serial"))]
+    let serial: Option<String> = None;
+    // Live data on its own starts from nothing rather than waiting for stdin.
+    let mut data = if (cli.tcp.is_some() || serial.is_some()) && cli.filenames.is_empty() {
+        Data {
+            load_options: options.load_options(),
+            ..Data::new()
+        }
+    } else {
+        Data::load_filenames(&cli.filenames, options.load_options())
+    };
+    let mut graph_options = options.graph_options();
+    if graph_options.title.is_none() {
+        graph_options.title = log_title(&cli.filenames);
+    }
+    // The window keeps the data as loaded so that despiking can be switched on and off there.
+    if let (true, Some(max_delta)) = (headless, options.despike) {
+        data.despike(max_delta);
+    }
+    // Spikes are removed first so that they aren't smeared across their neighbours.
+    if let Some(tau) = options.smoothing {
+        data = data.smoothed(tau);
+    }
 
-    let (data_min_timestamp, data_max_timestamp) = data
-        .data
-        .iter()
-        .map(|a| (a.timestamp, a.timestamp))
-        .reduce(|a, b| (a.0.min(b.0), a.1.max(b.1)))
-        .unwrap();
-
-    let ui = AppWindow::new()?;
-
-    ui.set_graph_image_height(GRAPH_IMAGE_HEIGHT as f32);
-    ui.set_graph_image_width(GRAPH_IMAGE_WIDTH as f32);
-    ui.set_graph_image(data.graph(
-        GRAPH_IMAGE_WIDTH,
-        GRAPH_IMAGE_HEIGHT,
-        data_min_timestamp,
-        data_max_timestamp,
-    ));
-
-    // The absolute minimum and maximum times for the entire data set
-    ui.set_data_minimum_time(SharedString::from(data_min_timestamp.to_rfc3339()));
-    ui.set_data_maximum_time(SharedString::from(data_max_timestamp.to_rfc3339()));
-
-    // The minimum and maximum displayed times
-    ui.set_display_timestamp_min(SharedString::from(data_min_timestamp.to_rfc3339()));
-    ui.set_display_timestamp_max(SharedString::from(data_max_timestamp.to_rfc3339()));
-
-    let max_time_interval = data_max_timestamp - data_min_timestamp;
-
-    ui.set_display_scroller_max_value(max_time_interval.num_seconds() as f32);
-    ui.set_display_start_scroller_value(0f32);
-    ui.set_display_end_scroller_value(max_time_interval.num_seconds() as f32);
-
-    ui.on_redraw_graph({
-        let ui_weak = ui.as_weak();
-        let data_min_timestamp = data_min_timestamp.clone();
-        let data_max_timestamp = data_max_timestamp.clone();
-        let data = data.clone();
-        move || {
-            let ui = ui_weak.unwrap();
-            let a = ui.get_display_timestamp_min();
-            let a1 = a.as_str();
-            let b = ui.get_display_timestamp_max();
-            let b1 = b.as_str();
-            match DateTime::parse_from_rfc3339(a1) {
-                Ok(mut min_timestamp) => {
-                    if min_timestamp < data_min_timestamp {
-                        min_timestamp = data_min_timestamp.fixed_offset()
-                    };
-                    match DateTime::parse_from_rfc3339(b1) {
-                        Ok(mut max_timestamp) => {
-                            if max_timestamp > data_max_timestamp {
-                                max_timestamp = data_max_timestamp.fixed_offset()
-                            };
-                            ui.set_graph_image(data.graph(
-                                GRAPH_IMAGE_WIDTH,
-                                GRAPH_IMAGE_HEIGHT,
-                                min_timestamp.to_utc(),
-                                max_timestamp.to_utc(),
-                            ));
-                        }
-                        Err(e) => eprintln!("{e:?}"),
-                    }
-                }
-                Err(e) => eprintln!("{e:?}"),
-            }
+    let comparison = cli.compare.as_ref().map(|filename| {
+        let mut comparison =
+            Data::load_filenames(std::slice::from_ref(filename), options.load_options());
+        if let (true, Some(max_delta)) = (headless, options.despike) {
+            comparison.despike(max_delta);
+        }
+        if let Some(tau) = options.smoothing {
+            comparison = comparison.smoothed(tau);
         }
+        comparison.aligned_to(&data)
     });
 
-    ui.on_scroller_changed({
-        let ui_weak = ui.as_weak();
-        let data_min_timestamp = data_min_timestamp.clone();
-        move || {
-            let ui = ui_weak.unwrap();
-            let start_offset: f32 = ui.get_display_start_scroller_value();
-            let end_offset: f32 = ui.get_display_end_scroller_value();
-            let start_delta = TimeDelta::seconds(start_offset as i64);
-            let end_delta = TimeDelta::seconds(end_offset as i64);
-            let start_time = data_min_timestamp + start_delta;
-            let end_time = data_min_timestamp + end_delta;
-            ui.set_display_timestamp_min(SharedString::from(start_time.to_rfc3339()));
-            ui.set_display_timestamp_max(SharedString::from(end_time.to_rfc3339()));
+    if cli.inspect {
+        print_inspection(&data);
+    }
+
+    if cli.by_point_of_sail {
+        let modes = options.sailing_modes();
+        let comparisons = comparison.as_ref().map(|a| a.by_point_of_sail(&modes));
+        for (index, (point_of_sail, points)) in data.by_point_of_sail(&modes).iter().enumerate() {
+            let comparison = comparisons.as_ref().map(|a| &a[index].1);
+            write_polars(&cli, &options, points, comparison, Some(*point_of_sail));
         }
-    });
+    } else {
+        write_polars(&cli, &options, &data, comparison.as_ref(), None);
+    }
+
+    if let Some(path) = &cli.csv {
+        if let Err(e) = fs::write(path, data.to_csv(graph_options.units)) {
+            error!("{}: {e}", path.display());
+            exit(-1);
+        }
+    }
+
+    if let Some(path) = &cli.json {
+        let written = data
+            .to_json()
+            .map_err(|e| format!("{e:?}"))
+            .and_then(|json| fs::write(path, json).map_err(|e| format!("{e:?}")));
+        if let Err(e) = written {
+            error!("{}: {e}", path.display());
+            exit(-1);
+        }
+    }
+
+    if let Some(path) = &cli.gpx {
+        if let Err(e) = fs::write(path, data.to_gpx()) {
+            error!("{}: {e}", path.display());
+            exit(-1);
+        }
+    }
+
+    let range = view_range(&data, cli.start, cli.end);
+
+    if let Some(path) = &cli.output {
+        let (start, end) = range.unwrap_or(data.display_range());
+        let image = data.graph_with_comparison(
+            comparison.as_ref(),
+            options.width(),
+            options.height(),
+            start,
+            end,
+            &graph_options,
+        );
+        if let Err(e) = export::save_png(&image, path) {
+            error!("{}: {e}", path.display());
+            exit(-1);
+        }
+    }
+
+    if let Some(path) = &cli.svg {
+        let (start, end) = range.unwrap_or(data.display_range());
+        let svg = data.graph_svg(
+            options.width(),
+            options.height(),
+            start,
+            end,
+            &graph_options,
+        );
+        if let Err(e) = fs::write(path, svg) {
+            error!("{}: {e}", path.display());
+            exit(-1);
+        }
+    }
+
+    if let Some(path) = &cli.wind_rose {
+        let image = data.wind_rose_with_bands(cli.wind_rose_sectors, &options.tws_bands());
+        if let Err(e) = export::save_png(&image, path) {
+            error!("{}: {e}", path.display());
+            exit(-1);
+        }
+    }
+
+    if let Some(path) = &cli.twa_curve {
+        let (low, high) = cli.twa_curve_band;
+        let image = data.twa_curve_image(options.width(), options.height(), low, high);
+        if let Err(e) = export::save_png(&image, path) {
+            error!("{}: {e}", path.display());
+            exit(-1);
+        }
+    }
+
+    if headless {
+        if cli.summary {
+            print_summary(&data, &options.tws_bands());
+        } else {
+            print_statistics(&data);
+            print_points_of_sail(&data, &options.sailing_modes());
+            print_target_angles(&data, &options.tws_bands());
+        }
+        if cli.profile {
+            data.profile.report();
+        }
+        return Ok(());
+    }
+
+    #[cfg(feature = "gui")]
+    let follower = match (
+        serial,
+        cli.tcp.clone(),
+        cli.follow,
+        cli.filenames.as_slice(),
+    ) {
+        #[cfg(feature = "serial")]
+        (Some(device), _, _, _) => Some(Follower::open_serial(device, cli.baud)),
+        (_, Some(address), _, _) => Some(Follower::connect_tcp(address)),
+        (_, None, false, _) => None,
+        (_, None, true, [filename]) => match Follower::open(filename) {
+            Ok(follower) => Some(follower),
+            Err(e) => {
+                error!("{filename}: {e}");
+                exit(-1);
+            }
+        },
+        (_, None, true, _) => {
+            error!("--follow needs exactly one log file.");
+            exit(-1);
+        }
+    };
+
+    #[cfg(feature = "gui")]
+    gui::run(
+        data,
+        comparison,
+        graph_options,
+        &options,
+        follower,
+        options.width(),
+        options.height(),
+        range,
+        cli.profile,
+    )?;
+
+    Ok(())
+}
+
+// Writes --polar-csv and --polar for `data`, named for `point_of_sail` if it only has the
+// datapoints sailed at that.
+fn write_polars(
+    cli: &Cli,
+    options: &Options,
+    data: &Data,
+    comparison: Option<&Data>,
+    point_of_sail: Option<PointOfSail>,
+) {
+    let path_for = |path: &Path| match point_of_sail {
+        Some(point_of_sail) => point_of_sail.file_name(path),
+        None => path.to_path_buf(),
+    };
+    if let Some(path) = &cli.polar_csv {
+        let path = path_for(path);
+        let csv = data.export_polar_csv(&options.tws_bands(), POLAR_TWA_STEP);
+        if let Err(e) = fs::write(&path, csv) {
+            error!("{}: {e}", path.display());
+            exit(-1);
+        }
+    }
+    if let Some(path) = &cli.polar {
+        let path = path_for(path);
+        let image = data.polar_with_comparison(
+            comparison,
+            options.width(),
+            options.height(),
+            &options.tws_bands(),
+        );
+        if let Err(e) = export::save_png(&image, &path) {
+            error!("{}: {e}", path.display());
+            exit(-1);
+        }
+    }
+}
+
+// A band of true wind speeds written as "8,12".
+fn parse_tws_range(text: &str) -> Result<(f32, f32), String> {
+    let invalid = || format!("{text:?} isn't a range of wind speeds like 8,12");
+    let (low, high) = text.split_once(',').ok_or_else(invalid)?;
+    let low: f32 = low.trim().parse().map_err(|_| invalid())?;
+    let high: f32 = high.trim().parse().map_err(|_| invalid())?;
+    if high <= low {
+        return Err(format!(
+            "{text:?} has to go from a lower to a higher wind speed"
+        ));
+    }
+    Ok((low, high))
+}
+
+// Progress is logged by default.
+fn log_level(verbose: u8, quiet: u8) -> LevelFilter {
+    match (verbose, quiet) {
+        (0, 0) => LevelFilter::Info,
+        (0, 1) => LevelFilter::Warn,
+        (0, 2) => LevelFilter::Error,
+        (0, _) => LevelFilter::Off,
+        (1, _) => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+// A time given on the command line, either absolute or relative to the start of the data.
+#[derive(Debug, Clone, Copy)]
+enum TimeArg {
+    At(DateTime<Utc>),
+    After(TimeDelta),
+}
 
-    ui.run()
+impl FromStr for TimeArg {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<TimeArg, String> {
+        let Some(offset) = text.strip_prefix('+') else {
+            return text
+                .parse()
+                .map(TimeArg::At)
+                .map_err(|e| format!("{text:?} isn't an RFC3339 time: {e}"));
+        };
+        let invalid = || format!("{text:?} isn't an offset like +2h, +90m or +1h30m");
+        let mut total = TimeDelta::zero();
+        let mut number = String::new();
+        for c in offset.chars() {
+            if c.is_ascii_digit() {
+                number.push(c);
+                continue;
+            }
+            let count: i64 = number.parse().map_err(|_| invalid())?;
+            total += match c {
+                'h' => TimeDelta::hours(count),
+                'm' => TimeDelta::minutes(count),
+                's' => TimeDelta::seconds(count),
+                _ => return Err(invalid()),
+            };
+            number.clear();
+        }
+        if !number.is_empty() || offset.is_empty() {
+            return Err(invalid());
+        }
+        Ok(TimeArg::After(total))
+    }
+}
+
+impl TimeArg {
+    fn resolve(self, data_start: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            TimeArg::At(time) => time,
+            TimeArg::After(offset) => data_start + offset,
+        }
+    }
+}
+
+// The range given by --start and --end, either defaulting to that end of the data and clamped to
+// it, or None if neither was given or they leave nothing to show.
+fn view_range(
+    data: &Data,
+    start: Option<TimeArg>,
+    end: Option<TimeArg>,
+) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    if start.is_none() && end.is_none() {
+        return None;
+    }
+    let (first, last) = data.display_range();
+    let start = start.map_or(first, |a| a.resolve(first).clamp(first, last));
+    let end = end.map_or(last, |a| a.resolve(first).clamp(first, last));
+    if end <= start {
+        warn!("--end has to be after --start within the data, showing all of it instead.");
+        return None;
+    }
+    Some((start, end))
+}
+
+// The default graph title: the names of the logs without their folders, or nothing for stdin.
+fn log_title(filenames: &[String]) -> Option<String> {
+    if filenames.is_empty() {
+        return None;
+    }
+    let names: Vec<String> = filenames
+        .iter()
+        .map(|a| {
+            Path::new(a)
+                .file_name()
+                .map_or(a.clone(), |b| b.to_string_lossy().to_string())
+        })
+        .collect();
+    Some(names.join(", "))
+}
+
+fn print_statistics(data: &Data) {
+    match data.time_range() {
+        Some((first, last)) => println!(
+            "{} datapoints from {} to {}",
+            data.data.len(),
+            first.to_rfc3339(),
+            last.to_rfc3339()
+        ),
+        None => println!("No datapoints loaded."),
+    }
+    if !data.spikes.is_empty() {
+        println!("{} datapoints despiked", data.spikes.len());
+    }
+    let maneuvers = data.maneuvers();
+    let tacks = maneuvers
+        .iter()
+        .filter(|a| a.kind == ManeuverKind::Tack)
+        .count();
+    if !maneuvers.is_empty() {
+        println!("{tacks} tacks and {} gybes", maneuvers.len() - tacks);
+    }
+}
+
+fn print_summary(data: &Data, tws_bands: &[f32]) {
+    let Some(summary) = data.summary(tws_bands) else {
+        println!("No datapoints loaded.");
+        return;
+    };
+    let duration = summary.duration();
+    println!("Start      {}", summary.start.to_rfc3339());
+    println!("End        {}", summary.end.to_rfc3339());
+    println!(
+        "Duration   {}h {:02}m",
+        duration.num_hours(),
+        duration.num_minutes() % 60
+    );
+    println!("Datapoints {}", summary.datapoints);
+    for (name, range) in [
+        ("Boat speed", summary.boatspeed),
+        ("Wind speed", summary.windspeed),
+    ] {
+        match range {
+            Some(range) => println!(
+                "{name} min {:.1} mean {:.1} max {:.1}",
+                range.min, range.mean, range.max
+            ),
+            None => println!("{name} -"),
+        }
+    }
+    match summary.distance_made_good {
+        Some(made_good) => println!(
+            "Distance   {:.2} NM sailed, {made_good:.2} NM made good",
+            summary.distance_sailed
+        ),
+        None => println!("Distance   {:.2} NM sailed", summary.distance_sailed),
+    }
+    if let Some((set, drift)) = data.mean_current() {
+        println!("Current    {drift:.1} kn setting {set:.0}");
+    }
+    for (band, percentage) in summary.tws_bands {
+        println!("TWS {band:>4} {percentage:>5.1}%");
+    }
+}
+
+fn print_points_of_sail(data: &Data, modes: &SailingModes) {
+    if data.data.is_empty() {
+        return;
+    }
+    for (point_of_sail, points) in data.by_point_of_sail(modes) {
+        println!(
+            "{:<9} {:>8} datapoints {:>5.1}%",
+            point_of_sail.name(),
+            points.data.len(),
+            points.data.len() as f32 * 100. / data.data.len() as f32
+        );
+    }
+}
+
+fn print_target_angles(data: &Data, tws_bands: &[f32]) {
+    if data.data.is_empty() {
+        return;
+    }
+    let format = |target: Option<Target>| match target {
+        Some(target) => format!(
+            "{:>5.0} {:>6.2} {:>6.2}",
+            target.twa, target.boatspeed, target.vmg
+        ),
+        None => format!("{:>5} {:>6} {:>6}", "-", "-", "-"),
+    };
+    println!(
+        "{:>5}  {:>5} {:>6} {:>6}  {:>5} {:>6} {:>6}",
+        "TWS", "Up", "Speed", "VMG", "Down", "Speed", "VMG"
+    );
+    for targets in data.target_angles(tws_bands) {
+        println!(
+            "{:>5}  {}  {}",
+            targets.tws,
+            format(targets.upwind),
+            format(targets.downwind)
+        );
+    }
+}
+
+fn print_inspection(data: &Data) {
+    if data.unhandled_sentences.is_empty() {
+        println!("Every sentence type read was used.");
+    } else {
+        println!("Sentence types read but not used:");
+        for (sentence_type, count) in &data.unhandled_sentences {
+            println!("  {sentence_type:<5} {count:>8}");
+        }
+    }
+    // Every bad checksum is skipped when they're rejected, leaving the rest of the skipped lines
+    // as ones that couldn't be parsed.
+    let checksum_skips = if data.load_options.reject_bad_checksums {
+        data.bad_checksums
+    } else {
+        0
+    };
+    if data.bad_checksums > 0 {
+        println!(
+            "{} sentences had bad checksums, {checksum_skips} of them skipped",
+            data.bad_checksums
+        );
+    }
+    let unparsed = data.skipped_lines.len().saturating_sub(checksum_skips);
+    if unparsed > 0 {
+        println!("{unparsed} lines couldn't be parsed and were skipped");
+    }
+    if data.normalized_angles > 0 {
+        println!(
+            "Warning: {} wind angles were outside 0-360 and have been wrapped",
+            data.normalized_angles
+        );
+    }
 }
@@ -0,0 +1,110 @@
+use crate::canvas::Rgb8Pixel;
+use crate::datapoints::{Data, DataPoint};
+use chrono::{DateTime, TimeDelta, Utc};
+
+// How long the wind has to stay on one side of the boat before a change of side counts as a
+// tack or gybe, so that noise around head to wind or dead downwind isn't reported.
+pub const MINIMUM_MANEUVER_DWELL_SECONDS: i64 = 10;
+
+const TACK_COLOUR: Rgb8Pixel = Rgb8Pixel {
+    r: 0x00,
+    g: 0xc0,
+    b: 0xff,
+};
+const GYBE_COLOUR: Rgb8Pixel = Rgb8Pixel {
+    r: 0xff,
+    g: 0x60,
+    b: 0x00,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManeuverKind {
+    // Turning through head to wind.
+    Tack,
+    // Turning through dead downwind.
+    Gybe,
+}
+
+impl ManeuverKind {
+    pub fn colour(&self) -> Rgb8Pixel {
+        match self {
+            ManeuverKind::Tack => TACK_COLOUR,
+            ManeuverKind::Gybe => GYBE_COLOUR,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Maneuver {
+    // The first reading on the new tack.
+    pub timestamp: DateTime<Utc>,
+    pub kind: ManeuverKind,
+}
+
+// A stretch of readings with the wind on the same side of the boat.
+struct Run {
+    port: bool,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    // Folded to 0-180, at the start and end of the run.
+    first_angle: f32,
+    last_angle: f32,
+}
+
+// Finds every change of tack in `points`, which need to be in time order. Stretches on one tack
+// shorter than `min_dwell` are ignored, so a wind angle wavering either side of zero only counts
+// once the boat settles on the new tack.
+pub fn detect_maneuvers<'a>(
+    points: impl IntoIterator<Item = &'a DataPoint>,
+    min_dwell: TimeDelta,
+) -> Vec<Maneuver> {
+    let mut runs: Vec<Run> = Vec::new();
+    for point in points {
+        let Some(angle) = point.winddirection else {
+            continue;
+        };
+        let port = angle > 180.;
+        let folded = if port { 360. - angle } else { angle };
+        match runs.last_mut() {
+            Some(run) if run.port == port => {
+                run.end = point.timestamp;
+                run.last_angle = folded;
+            }
+            _ => runs.push(Run {
+                port,
+                start: point.timestamp,
+                end: point.timestamp,
+                first_angle: folded,
+                last_angle: folded,
+            }),
+        }
+    }
+
+    let mut maneuvers = Vec::new();
+    let mut previous: Option<&Run> = None;
+    for run in runs.iter().filter(|a| a.end - a.start >= min_dwell) {
+        if let Some(previous) = previous.filter(|a| a.port != run.port) {
+            // Which way the boat turned shows in the angles either side of the change.
+            let kind = if previous.last_angle + run.first_angle < 180. {
+                ManeuverKind::Tack
+            } else {
+                ManeuverKind::Gybe
+            };
+            maneuvers.push(Maneuver {
+                timestamp: run.start,
+                kind,
+            });
+        }
+        previous = Some(run);
+    }
+    maneuvers
+}
+
+impl Data {
+    pub fn maneuvers(&self) -> Vec<Maneuver> {
+        detect_maneuvers(
+            &self.data,
+            TimeDelta::seconds(MINIMUM_MANEUVER_DWELL_SECONDS),
+        )
+    }
+}
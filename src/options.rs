@@ -0,0 +1,333 @@
+use crate::channels::{Channel, HexColour, Palette, SeriesColours};
+use crate::datapoints::LoadOptions;
+use crate::graph::{GraphOptions, LegendPosition, Theme};
+use crate::polar::{SailingModes, DEFAULT_TWS_BANDS};
+use crate::units::SpeedUnit;
+use clap::Args;
+use log::error;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::exit;
+
+// Looked for in the current directory when no `--config` is given.
+pub const CONFIG_FILENAME: &str = "make-polar.toml";
+// Looked for in the user's config directory when there's no config file in the current one.
+pub const USER_CONFIG_PATH: &str = "make-polar/config.toml";
+
+pub const GRAPH_IMAGE_WIDTH: u32 = 1000;
+pub const GRAPH_IMAGE_HEIGHT: u32 = 400;
+
+// Limits on the graph size, small enough to leave room for the axes and large enough for a
+// poster without the bin arithmetic misbehaving.
+pub const MINIMUM_GRAPH_SIZE: i64 = 100;
+pub const MAXIMUM_GRAPH_SIZE: i64 = 20000;
+
+// Every setting that can come from either the command line or a config file. Anything left
+// unset on the command line falls back to the config file, then to the built in default.
+#[derive(Debug, Clone, Default, Args, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Options {
+    /// Width of the graph in pixels [default: 1000]
+    #[arg(long, value_parser = clap::value_parser!(u32).range(MINIMUM_GRAPH_SIZE..=MAXIMUM_GRAPH_SIZE))]
+    pub width: Option<u32>,
+
+    /// Height of the graph in pixels [default: 400]
+    #[arg(long, value_parser = clap::value_parser!(u32).range(MINIMUM_GRAPH_SIZE..=MAXIMUM_GRAPH_SIZE))]
+    pub height: Option<u32>,
+
+    /// Scale the speed axis to this percentile of the displayed speeds instead of the maximum
+    #[arg(long)]
+    pub scale_percentile: Option<f32>,
+
+    /// Keep wind readings from different instruments separate and plot each of them
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    pub merge_sources: Option<bool>,
+
+    /// Shade night time behind the graph, based on the logged position
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    pub daynight: Option<bool>,
+
+    /// The channels to plot [default: boat-speed,wind-speed,wind-direction]
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub channels: Option<Vec<Channel>>,
+
+    /// Fold wind angles onto 0-180, with port tack a shade darker, instead of plotting 0-360
+    /// [default: true]
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    pub fold_wind_direction: Option<bool>,
+
+    /// Plot wind angles in their own panel below the speeds instead of over them
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    pub stacked: Option<bool>,
+
+    /// Draw a moving average of each speed over this many seconds
+    #[arg(long)]
+    pub moving_average: Option<u32>,
+
+    /// The unit speeds are shown and exported in [default: knots]
+    #[arg(long, value_enum)]
+    pub units: Option<SpeedUnit>,
+
+    /// Mark tacks and gybes on the graph [default: true]
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    pub maneuvers: Option<bool>,
+
+    /// Mark gusts and lulls on the wind speed trace [default: false]
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    pub gusts: Option<bool>,
+
+    /// Knots above or below the running mean wind speed that count as a gust or lull [default: 3]
+    #[arg(long)]
+    pub gust_threshold: Option<f32>,
+
+    /// Seconds within which only the biggest gust, or lull, is marked [default: 30]
+    #[arg(long)]
+    pub gust_separation: Option<u32>,
+
+    /// Draw the graph dark on white for printing, or light on black [default: dark]
+    #[arg(long, value_enum)]
+    pub theme: Option<Theme>,
+
+    /// Colours for boat speed, wind speed and wind direction [default: default]
+    #[arg(long, value_enum)]
+    pub palette: Option<Palette>,
+
+    /// Boat speed colour as #rrggbb, overriding the palette
+    #[arg(long)]
+    pub boat_speed_colour: Option<HexColour>,
+
+    /// Wind speed colour as #rrggbb, overriding the palette
+    #[arg(long)]
+    pub wind_speed_colour: Option<HexColour>,
+
+    /// Wind direction colour as #rrggbb, overriding the palette
+    #[arg(long)]
+    pub wind_direction_colour: Option<HexColour>,
+
+    /// Thickness in pixels of the lines drawn through each series [default: 1]
+    #[arg(long)]
+    pub line_width: Option<u32>,
+
+    /// Smooth the edges of the lines drawn through each series
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    pub antialias: Option<bool>,
+
+    /// Print the log's file name and the displayed time range at the top of the graph
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    pub titles: Option<bool>,
+
+    /// Title for the top of the graph instead of the file name, implying --titles
+    #[arg(long)]
+    pub title: Option<String>,
+
+    /// Subtitle for the top of the graph instead of the time range, implying --titles
+    #[arg(long)]
+    pub subtitle: Option<String>,
+
+    /// Where to put the legend on the graph [default: top-right]
+    #[arg(long, value_enum)]
+    pub legend: Option<LegendPosition>,
+
+    /// Treat a wind speed of zero as a missing reading rather than a calm
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    pub wind_zero_missing: Option<bool>,
+
+    /// Treat a boat speed of zero as a missing reading rather than being stopped
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    pub boat_zero_missing: Option<bool>,
+
+    /// Stop at the first line that can't be parsed instead of skipping it
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    pub strict: Option<bool>,
+
+    /// Skip sentences whose checksum doesn't match instead of only counting them
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    pub reject_bad_checksums: Option<bool>,
+
+    /// Remove single boat or wind speed readings that jump more than this many knots from the
+    /// readings either side of them
+    #[arg(long)]
+    pub despike: Option<f32>,
+
+    /// Smooth boat speed, wind speed and wind angle with a low-pass filter of this time constant
+    /// in seconds
+    #[arg(long)]
+    pub smoothing: Option<f32>,
+
+    /// True wind angles below this are sailing upwind [default: 70]
+    #[arg(long)]
+    pub upwind_max: Option<f32>,
+
+    /// True wind angles above this are sailing downwind [default: 120]
+    #[arg(long)]
+    pub downwind_min: Option<f32>,
+
+    /// True wind speeds, in knots, that the polar is divided into [default: 6,8,10,12,16,20]
+    #[arg(long, value_delimiter = ',')]
+    pub tws_bands: Option<Vec<f32>>,
+}
+
+impl Options {
+    // Reads the config file at `path`, or else the first of `make-polar.toml` in the current
+    // directory and `make-polar/config.toml` in the user's config directory that exists. An
+    // explicitly named file has to exist.
+    pub fn load_config(path: Option<&Path>) -> Options {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => {
+                let found = std::iter::once(PathBuf::from(CONFIG_FILENAME))
+                    .chain(user_config_dir().map(|a| a.join(USER_CONFIG_PATH)))
+                    .find(|a| a.exists());
+                let Some(path) = found else {
+                    return Options::default();
+                };
+                path
+            }
+        };
+        match fs::read_to_string(&path) {
+            Ok(text) => match toml::from_str(&text) {
+                Ok(options) => options,
+                Err(e) => {
+                    error!("{}: {e}", path.display());
+                    exit(-1);
+                }
+            },
+            Err(e) => {
+                error!("{}: {e}", path.display());
+                exit(-1);
+            }
+        }
+    }
+
+    // Fills anything not set here from `fallback`.
+    pub fn or(self, fallback: Options) -> Options {
+        Options {
+            width: self.width.or(fallback.width),
+            height: self.height.or(fallback.height),
+            scale_percentile: self.scale_percentile.or(fallback.scale_percentile),
+            merge_sources: self.merge_sources.or(fallback.merge_sources),
+            daynight: self.daynight.or(fallback.daynight),
+            channels: self.channels.or(fallback.channels),
+            units: self.units.or(fallback.units),
+            maneuvers: self.maneuvers.or(fallback.maneuvers),
+            gusts: self.gusts.or(fallback.gusts),
+            gust_threshold: self.gust_threshold.or(fallback.gust_threshold),
+            gust_separation: self.gust_separation.or(fallback.gust_separation),
+            theme: self.theme.or(fallback.theme),
+            palette: self.palette.or(fallback.palette),
+            boat_speed_colour: self.boat_speed_colour.or(fallback.boat_speed_colour),
+            wind_speed_colour: self.wind_speed_colour.or(fallback.wind_speed_colour),
+            wind_direction_colour: self
+                .wind_direction_colour
+                .or(fallback.wind_direction_colour),
+            line_width: self.line_width.or(fallback.line_width),
+            antialias: self.antialias.or(fallback.antialias),
+            titles: self.titles.or(fallback.titles),
+            title: self.title.or(fallback.title),
+            subtitle: self.subtitle.or(fallback.subtitle),
+            legend: self.legend.or(fallback.legend),
+            moving_average: self.moving_average.or(fallback.moving_average),
+            stacked: self.stacked.or(fallback.stacked),
+            fold_wind_direction: self.fold_wind_direction.or(fallback.fold_wind_direction),
+            wind_zero_missing: self.wind_zero_missing.or(fallback.wind_zero_missing),
+            boat_zero_missing: self.boat_zero_missing.or(fallback.boat_zero_missing),
+            strict: self.strict.or(fallback.strict),
+            reject_bad_checksums: self.reject_bad_checksums.or(fallback.reject_bad_checksums),
+            despike: self.despike.or(fallback.despike),
+            smoothing: self.smoothing.or(fallback.smoothing),
+            upwind_max: self.upwind_max.or(fallback.upwind_max),
+            downwind_min: self.downwind_min.or(fallback.downwind_min),
+            tws_bands: self.tws_bands.or(fallback.tws_bands),
+        }
+    }
+
+    // A config file isn't checked against the command line's limits, so its sizes are kept to
+    // them here.
+    pub fn width(&self) -> u32 {
+        self.width
+            .unwrap_or(GRAPH_IMAGE_WIDTH)
+            .clamp(MINIMUM_GRAPH_SIZE as u32, MAXIMUM_GRAPH_SIZE as u32)
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+            .unwrap_or(GRAPH_IMAGE_HEIGHT)
+            .clamp(MINIMUM_GRAPH_SIZE as u32, MAXIMUM_GRAPH_SIZE as u32)
+    }
+
+    pub fn load_options(&self) -> LoadOptions {
+        LoadOptions {
+            merge_sources: self.merge_sources.unwrap_or(false),
+            wind_zero_missing: self.wind_zero_missing.unwrap_or(false),
+            boat_zero_missing: self.boat_zero_missing.unwrap_or(false),
+            strict: self.strict.unwrap_or(false),
+            reject_bad_checksums: self.reject_bad_checksums.unwrap_or(false),
+        }
+    }
+
+    pub fn sailing_modes(&self) -> SailingModes {
+        let defaults = SailingModes::default();
+        SailingModes {
+            upwind_max: self.upwind_max.unwrap_or(defaults.upwind_max),
+            downwind_min: self.downwind_min.unwrap_or(defaults.downwind_min),
+        }
+    }
+
+    pub fn tws_bands(&self) -> Vec<f32> {
+        self.tws_bands
+            .clone()
+            .unwrap_or_else(|| DEFAULT_TWS_BANDS.to_vec())
+    }
+
+    pub fn series_colours(&self) -> SeriesColours {
+        let palette = self
+            .palette
+            .unwrap_or_default()
+            .series_colours(self.theme.unwrap_or_default());
+        SeriesColours {
+            boat_speed: self.boat_speed_colour.map_or(palette.boat_speed, |a| a.0),
+            wind_speed: self.wind_speed_colour.map_or(palette.wind_speed, |a| a.0),
+            wind_direction: self
+                .wind_direction_colour
+                .map_or(palette.wind_direction, |a| a.0),
+        }
+    }
+
+    pub fn graph_options(&self) -> GraphOptions {
+        let defaults = GraphOptions::default();
+        GraphOptions {
+            channels: self.channels.clone().unwrap_or(defaults.channels),
+            scale_percentile: self.scale_percentile.or(defaults.scale_percentile),
+            daynight: self.daynight.unwrap_or(defaults.daynight),
+            units: self.units.unwrap_or(defaults.units),
+            maneuvers: self.maneuvers.unwrap_or(defaults.maneuvers),
+            gusts: self.gusts.unwrap_or(defaults.gusts),
+            gust_threshold: self.gust_threshold.unwrap_or(defaults.gust_threshold),
+            gust_separation: self.gust_separation.unwrap_or(defaults.gust_separation),
+            colours: self.series_colours(),
+            theme: self.theme.unwrap_or(defaults.theme),
+            line_width: self.line_width.unwrap_or(defaults.line_width),
+            antialias: self.antialias.unwrap_or(defaults.antialias),
+            titles: self
+                .titles
+                .unwrap_or(self.title.is_some() || self.subtitle.is_some()),
+            title: self.title.clone(),
+            subtitle: self.subtitle.clone(),
+            legend: self.legend.unwrap_or(defaults.legend),
+            moving_average: self.moving_average.or(defaults.moving_average),
+            stacked: self.stacked.unwrap_or(defaults.stacked),
+            fold_wind_direction: self
+                .fold_wind_direction
+                .unwrap_or(defaults.fold_wind_direction),
+        }
+    }
+}
+
+// $XDG_CONFIG_HOME, or ~/.config without it.
+fn user_config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|a| !a.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|a| PathBuf::from(a).join(".config")))
+}
@@ -0,0 +1,183 @@
+use crate::canvas::{Canvas, Rgb8Pixel};
+use crate::datapoints::DataPoint;
+use crate::export::Pixels;
+use chrono::{DateTime, Utc};
+
+pub const OVERVIEW_WIDTH: u32 = 1000;
+pub const OVERVIEW_HEIGHT: u32 = 40;
+
+const OVERVIEW_BOAT_SPEED_COLOUR: Rgb8Pixel = Rgb8Pixel {
+    r: 0,
+    g: 0x80,
+    b: 0,
+};
+const OVERVIEW_WIND_SPEED_COLOUR: Rgb8Pixel = Rgb8Pixel {
+    r: 0x80,
+    g: 0x80,
+    b: 0x80,
+};
+
+// How many times finer than the columns the readings are kept at, so that columns can be
+// redrawn across a longer time range without going back to the datapoints.
+const BUCKETS_PER_COLUMN: usize = 4;
+
+// The low and high boat and wind speeds falling into one pixel column of the overview strip,
+// or None for either if none of its datapoints had that reading.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OverviewColumn {
+    pub boatspeed: Option<(f32, f32)>,
+    pub windspeed: Option<(f32, f32)>,
+}
+
+// A coarse aggregate of the whole data set at the overview strip's fixed resolution. It is
+// built in a single pass when the data is loaded, so drawing the strip never has to revisit
+// the individual datapoints, and datapoints arriving later are added to it on their own.
+#[derive(Debug, Clone)]
+pub struct Overview {
+    pub columns: Vec<Option<OverviewColumn>>,
+    // The readings in equal stretches of time from `earliest`, each `bucket_milliseconds` long.
+    // There are never more than `BUCKETS_PER_COLUMN` for each column, so the stretches double in
+    // length, two merging into one, whenever the data outgrows them.
+    buckets: Vec<Option<OverviewColumn>>,
+    bucket_milliseconds: i64,
+    earliest: Option<DateTime<Utc>>,
+    latest: Option<DateTime<Utc>>,
+}
+
+impl Overview {
+    pub fn new(data: &[DataPoint], width: u32) -> Overview {
+        let mut overview = Overview {
+            columns: vec![None; width as usize],
+            buckets: Vec::new(),
+            bucket_milliseconds: 0,
+            earliest: None,
+            latest: None,
+        };
+        overview.extend(data, 0);
+        overview
+    }
+
+    // Adds the datapoints in `data` from `from` on, which were appended since the overview was
+    // last brought up to date. One earlier than anything already in it moves the start of the
+    // strip, and everything is gone over again.
+    pub fn extend(&mut self, data: &[DataPoint], from: usize) {
+        if self.columns.is_empty() {
+            return;
+        }
+        let appended = &data[from.min(data.len())..];
+        let Some((earliest, latest)) = appended
+            .iter()
+            .map(|a| (a.timestamp, a.timestamp))
+            .reduce(|a, b| (a.0.min(b.0), a.1.max(b.1)))
+        else {
+            return;
+        };
+        if self.earliest.is_some_and(|a| earliest < a) {
+            *self = Overview::new(data, self.columns.len() as u32);
+            return;
+        }
+        let earliest = *self.earliest.get_or_insert(earliest);
+        let latest = self.latest.map_or(latest, |a| a.max(latest));
+        self.latest = Some(latest);
+
+        let most_buckets = (self.columns.len() * BUCKETS_PER_COLUMN).max(1) as i64;
+        if self.bucket_milliseconds == 0 {
+            let time_range_milliseconds = (latest - earliest).num_milliseconds() + 1;
+            self.bucket_milliseconds = (time_range_milliseconds + most_buckets - 1) / most_buckets;
+        }
+        while (latest - earliest).num_milliseconds() / self.bucket_milliseconds >= most_buckets {
+            self.buckets = self
+                .buckets
+                .chunks(2)
+                .map(|a| a.iter().flatten().copied().reduce(merged))
+                .collect();
+            self.bucket_milliseconds *= 2;
+        }
+        for point in appended {
+            let index = ((point.timestamp - earliest).num_milliseconds() / self.bucket_milliseconds)
+                as usize;
+            if self.buckets.len() <= index {
+                self.buckets.resize(index + 1, None);
+            }
+            let bucket = self.buckets[index].get_or_insert_with(OverviewColumn::default);
+            bucket.boatspeed = widened(bucket.boatspeed, point.boatspeed);
+            bucket.windspeed = widened(bucket.windspeed, point.windspeed);
+        }
+
+        // Each bucket goes in the column nearest its middle.
+        let width = self.columns.len() as i64;
+        let time_range_milliseconds = (latest - earliest).num_milliseconds().max(1);
+        self.columns = vec![None; width as usize];
+        for (index, bucket) in self.buckets.iter().enumerate() {
+            let Some(bucket) = bucket else {
+                continue;
+            };
+            let middle = index as i64 * self.bucket_milliseconds + self.bucket_milliseconds / 2;
+            let x = ((middle * (width - 1) + time_range_milliseconds / 2) / time_range_milliseconds)
+                .min(width - 1) as usize;
+            self.columns[x] = Some(match self.columns[x] {
+                Some(column) => merged(column, *bucket),
+                None => *bucket,
+            });
+        }
+    }
+
+    pub fn image(&self, height: u32) -> Pixels {
+        let mut graphicimage = Pixels::new(self.columns.len() as u32, height);
+        let largest_speed = self
+            .columns
+            .iter()
+            .flatten()
+            .flat_map(|a| [a.boatspeed, a.windspeed])
+            .flatten()
+            .map(|a| a.1)
+            .fold(0f32, f32::max);
+        let speed_ratio = (height - 1) as f32 / (largest_speed.floor() + 1f32);
+
+        for (x, column) in self.columns.iter().enumerate() {
+            if let Some(column) = column {
+                for (range, colour) in [
+                    (column.windspeed, OVERVIEW_WIND_SPEED_COLOUR),
+                    (column.boatspeed, OVERVIEW_BOAT_SPEED_COLOUR),
+                ] {
+                    let Some(range) = range else {
+                        continue;
+                    };
+                    graphicimage.line_from_to(
+                        (x as u32, (range.0 * speed_ratio) as u32),
+                        (x as u32, (range.1 * speed_ratio) as u32),
+                        colour,
+                    );
+                }
+            }
+        }
+        graphicimage
+    }
+}
+
+impl Default for Overview {
+    fn default() -> Self {
+        Overview::new(&[], OVERVIEW_WIDTH)
+    }
+}
+
+// Both columns' readings in one.
+fn merged(a: OverviewColumn, b: OverviewColumn) -> OverviewColumn {
+    let range = |a: Option<(f32, f32)>, b: Option<(f32, f32)>| {
+        let a = widened(a, b.map(|b| b.0));
+        widened(a, b.map(|b| b.1))
+    };
+    OverviewColumn {
+        boatspeed: range(a.boatspeed, b.boatspeed),
+        windspeed: range(a.windspeed, b.windspeed),
+    }
+}
+
+// `range` stretched to take in `value`, if there is one.
+fn widened(range: Option<(f32, f32)>, value: Option<f32>) -> Option<(f32, f32)> {
+    match (range, value) {
+        (Some(range), Some(value)) => Some((range.0.min(value), range.1.max(value))),
+        (None, Some(value)) => Some((value, value)),
+        (range, None) => range,
+    }
+}
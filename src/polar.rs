@@ -0,0 +1,510 @@
+use crate::canvas::{Canvas, Rgb8Pixel};
+use crate::datapoints::Data;
+use crate::export::Pixels;
+use crate::graph::{blend, percentile};
+use crate::text::{draw_text, text_height, text_width};
+use std::path::{Path, PathBuf};
+
+// Sector width, in degrees of true wind angle, used when drawing the polar.
+pub const POLAR_TWA_STEP: f32 = 5.;
+pub const DEFAULT_TWS_BANDS: [f32; 6] = [6., 8., 10., 12., 16., 20.];
+
+const POLAR_MARGIN: u32 = 30;
+// Each point of a TWA curve's fitted line averages the sector medians this many sectors either
+// side of it.
+const CURVE_SMOOTHING_SECTORS: usize = 2;
+// Scatter points are drawn this much fainter than the fitted line through them.
+const SCATTER_OPACITY: f32 = 0.4;
+// A comparison polar's curves are dimmed to this and drawn with every other sector left out.
+const COMPARISON_OPACITY: f32 = 0.6;
+const GRID_COLOUR: Rgb8Pixel = Rgb8Pixel {
+    r: 0x60,
+    g: 0x60,
+    b: 0x60,
+};
+pub(crate) const BAND_COLOURS: [Rgb8Pixel; 6] = [
+    Rgb8Pixel {
+        r: 0x40,
+        g: 0x80,
+        b: 0xff,
+    },
+    Rgb8Pixel {
+        r: 0x00,
+        g: 0xc0,
+        b: 0xc0,
+    },
+    Rgb8Pixel {
+        r: 0x00,
+        g: 0xff,
+        b: 0x00,
+    },
+    Rgb8Pixel {
+        r: 0xff,
+        g: 0xff,
+        b: 0x00,
+    },
+    Rgb8Pixel {
+        r: 0xff,
+        g: 0x80,
+        b: 0x00,
+    },
+    Rgb8Pixel {
+        r: 0xff,
+        g: 0x00,
+        b: 0x00,
+    },
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PointOfSail {
+    Upwind,
+    Reaching,
+    Downwind,
+}
+
+impl PointOfSail {
+    pub const ALL: [PointOfSail; 3] = [
+        PointOfSail::Upwind,
+        PointOfSail::Reaching,
+        PointOfSail::Downwind,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PointOfSail::Upwind => "Upwind",
+            PointOfSail::Reaching => "Reaching",
+            PointOfSail::Downwind => "Downwind",
+        }
+    }
+
+    // `path` with the point of sail added to the file name, like polar-upwind.csv for
+    // polar.csv.
+    pub fn file_name(&self, path: &Path) -> PathBuf {
+        let mut name = path.file_stem().unwrap_or_default().to_os_string();
+        name.push(format!("-{}", self.name().to_lowercase()));
+        if let Some(extension) = path.extension() {
+            name.push(".");
+            name.push(extension);
+        }
+        path.with_file_name(name)
+    }
+}
+
+// True wind angles, either side of the bow, dividing upwind from reaching and reaching from
+// downwind sailing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SailingModes {
+    pub upwind_max: f32,
+    pub downwind_min: f32,
+}
+
+impl Default for SailingModes {
+    fn default() -> Self {
+        SailingModes {
+            upwind_max: 70.,
+            downwind_min: 120.,
+        }
+    }
+}
+
+impl SailingModes {
+    pub fn classify(&self, true_wind_angle: f32) -> PointOfSail {
+        let angle = fold_angle(true_wind_angle);
+        if angle < self.upwind_max {
+            PointOfSail::Upwind
+        } else if angle > self.downwind_min {
+            PointOfSail::Downwind
+        } else {
+            PointOfSail::Reaching
+        }
+    }
+}
+
+// Folds an angle off the bow into 0-180 so that port and starboard are treated alike.
+pub fn fold_angle(angle: f32) -> f32 {
+    let angle = angle.rem_euclid(360.);
+    if angle > 180. {
+        360. - angle
+    } else {
+        angle
+    }
+}
+
+// The best VMG found at one point of sail, and the true wind angle and boat speed giving it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Target {
+    pub twa: f32,
+    pub boatspeed: f32,
+    // Always positive, upwind or downwind.
+    pub vmg: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TargetAngles {
+    pub tws: f32,
+    pub upwind: Option<Target>,
+    pub downwind: Option<Target>,
+}
+
+impl Data {
+    // Splits the datapoints by point of sail so that each can have its own polar.
+    pub fn by_point_of_sail(&self, modes: &SailingModes) -> Vec<(PointOfSail, Data)> {
+        PointOfSail::ALL
+            .iter()
+            .map(|point_of_sail| {
+                let points = self
+                    .data
+                    .iter()
+                    .filter(|a| {
+                        a.winddirection
+                            .is_some_and(|b| modes.classify(b) == *point_of_sail)
+                    })
+                    .cloned()
+                    .collect();
+                (*point_of_sail, Data::from_datapoints(points))
+            })
+            .collect()
+    }
+}
+
+impl Data {
+    // The median boat speed for each true wind speed band and true wind angle, indexed
+    // [band][sector] where sector n is centred on n * twa_step degrees, up to and including 180.
+    // Each datapoint counts towards the band nearest its true wind speed.
+    pub fn polar_cells(&self, tws_bands: &[f32], twa_step: f32) -> Vec<Vec<Option<f32>>> {
+        if tws_bands.is_empty() {
+            return Vec::new();
+        }
+        let sectors = (180. / twa_step).round() as usize + 1;
+        let mut speeds = vec![vec![Vec::new(); sectors]; tws_bands.len()];
+        for point in &self.data {
+            let (Some(boatspeed), Some(windspeed), Some(winddirection)) =
+                (point.boatspeed, point.windspeed, point.winddirection)
+            else {
+                continue;
+            };
+            let band = nearest_band(tws_bands, windspeed);
+            let sector = ((fold_angle(winddirection) / twa_step).round() as usize).min(sectors - 1);
+            speeds[band][sector].push(boatspeed);
+        }
+        speeds
+            .into_iter()
+            .map(|band| {
+                band.into_iter()
+                    .map(|mut a| (!a.is_empty()).then(|| percentile(&mut a, 50.)))
+                    .collect()
+            })
+            .collect()
+    }
+
+    // The upwind and downwind angles with the best VMG in each true wind speed band, from the
+    // polar cells.
+    pub fn target_angles(&self, tws_bands: &[f32]) -> Vec<TargetAngles> {
+        let cells = self.polar_cells(tws_bands, POLAR_TWA_STEP);
+        tws_bands
+            .iter()
+            .zip(cells)
+            .map(|(tws, band)| {
+                let targets: Vec<Target> = band
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(sector, speed)| {
+                        let twa = sector as f32 * POLAR_TWA_STEP;
+                        speed.map(|boatspeed| Target {
+                            twa,
+                            boatspeed,
+                            vmg: boatspeed * twa.to_radians().cos(),
+                        })
+                    })
+                    .collect();
+                let best = |upwind: bool| {
+                    targets
+                        .iter()
+                        .filter(|a| (a.twa < 90.) == upwind && (a.twa != 90.))
+                        .map(|a| Target {
+                            vmg: a.vmg.abs(),
+                            ..*a
+                        })
+                        .max_by(|a, b| a.vmg.total_cmp(&b.vmg))
+                };
+                TargetAngles {
+                    tws: *tws,
+                    upwind: best(true),
+                    downwind: best(false),
+                }
+            })
+            .collect()
+    }
+
+    // The polar as a table for routing software: a header row of the true wind speeds, then a
+    // row for each true wind angle. Cells without any data are written as 0.
+    pub fn export_polar_csv(&self, tws_bands: &[f32], twa_step: f32) -> String {
+        let cells = self.polar_cells(tws_bands, twa_step);
+        let mut csv = String::from("twa/tws");
+        for band in tws_bands {
+            csv += &format!(",{band}");
+        }
+        csv += "\n";
+        let sectors = cells.first().map(|a| a.len()).unwrap_or(0);
+        for sector in 0..sectors {
+            csv += &format!("{}", sector as f32 * twa_step);
+            for band in &cells {
+                csv += &format!(",{:.2}", band[sector].unwrap_or(0.));
+            }
+            csv += "\n";
+        }
+        csv
+    }
+
+    // Boat speed against true wind angle, with one curve per true wind speed band. The bow is
+    // at the top and port and starboard are folded together onto the right hand side.
+    pub fn polar(&self, width: u32, height: u32, tws_bands: &[f32]) -> Pixels {
+        self.polar_with_comparison(None, width, height, tws_bands)
+    }
+
+    // The polar with `comparison`'s curves drawn over it in the same band colours, dimmed and
+    // dashed.
+    pub fn polar_with_comparison(
+        &self,
+        comparison: Option<&Data>,
+        width: u32,
+        height: u32,
+        tws_bands: &[f32],
+    ) -> Pixels {
+        let mut graphicimage = Pixels::new(width, height);
+        let radius = width
+            .saturating_sub(2 * POLAR_MARGIN)
+            .min(height.saturating_sub(2 * POLAR_MARGIN) / 2) as f32;
+        if radius < 1. {
+            return graphicimage;
+        }
+        let cells = self.polar_cells(tws_bands, POLAR_TWA_STEP);
+        let comparison_cells = comparison
+            .map(|a| a.polar_cells(tws_bands, POLAR_TWA_STEP))
+            .unwrap_or_default();
+        let largest_speed = cells
+            .iter()
+            .chain(&comparison_cells)
+            .flatten()
+            .flatten()
+            .copied()
+            .fold(0f32, f32::max);
+        let ring_step = ((largest_speed.floor() + 1.) / 8.).ceil().max(1.) as u32;
+        let top_speed = (largest_speed.floor() as u32 + 1).div_ceil(ring_step) * ring_step;
+
+        let centre = (POLAR_MARGIN as f32, height as f32 / 2.);
+        let to_xy = |angle: f32, distance: f32| -> (u32, u32) {
+            let radians = angle.to_radians();
+            (
+                ((centre.0 + distance * radians.sin()) as u32).min(width - 1),
+                ((centre.1 + distance * radians.cos()).max(0.) as u32).min(height - 1),
+            )
+        };
+        let speed_distance = |speed: f32| speed * radius / top_speed as f32;
+
+        for speed in (ring_step..=top_speed).step_by(ring_step as usize) {
+            let distance = speed_distance(speed as f32);
+            let mut previous = to_xy(0., distance);
+            for angle in (5..=180).step_by(5) {
+                let next = to_xy(angle as f32, distance);
+                graphicimage.line_from_to(previous, next, GRID_COLOUR);
+                previous = next;
+            }
+            let label = speed.to_string();
+            let (x, y) = to_xy(90., distance);
+            draw_text(
+                &mut graphicimage,
+                x.saturating_sub(text_width(&label, 1) / 2),
+                y.saturating_sub(text_height(1) + 4),
+                &label,
+                1,
+                GRID_COLOUR,
+            );
+        }
+        let centre_xy = to_xy(0., 0.);
+        for angle in (0..=180).step_by(30) {
+            graphicimage.line_from_to(centre_xy, to_xy(angle as f32, radius), GRID_COLOUR);
+            let label = angle.to_string();
+            let (x, y) = to_xy(angle as f32, radius + 14.);
+            draw_text(
+                &mut graphicimage,
+                x.saturating_sub(text_width(&label, 1) / 2),
+                y.saturating_sub(text_height(1) / 2),
+                &label,
+                1,
+                GRID_COLOUR,
+            );
+        }
+
+        for (index, band) in comparison_cells.iter().enumerate() {
+            let colour = blend(
+                BAND_COLOURS[index % BAND_COLOURS.len()],
+                Rgb8Pixel { r: 0, g: 0, b: 0 },
+                COMPARISON_OPACITY,
+            );
+            let mut previous = None;
+            for (sector, speed) in band.iter().enumerate() {
+                let Some(speed) = speed else {
+                    continue;
+                };
+                let next = to_xy(sector as f32 * POLAR_TWA_STEP, speed_distance(*speed));
+                if let (Some(previous), true) = (previous, sector % 2 == 0) {
+                    graphicimage.line_from_to(previous, next, colour);
+                }
+                previous = Some(next);
+            }
+        }
+
+        for (index, band) in cells.iter().enumerate() {
+            let colour = BAND_COLOURS[index % BAND_COLOURS.len()];
+            let mut previous = None;
+            for (sector, speed) in band.iter().enumerate() {
+                let Some(speed) = speed else {
+                    continue;
+                };
+                let next = to_xy(sector as f32 * POLAR_TWA_STEP, speed_distance(*speed));
+                if let Some(previous) = previous {
+                    graphicimage.line_from_to(previous, next, colour);
+                }
+                previous = Some(next);
+            }
+
+            let label = format!("{} KT", tws_bands[index]);
+            draw_text(
+                &mut graphicimage,
+                width.saturating_sub(POLAR_MARGIN + text_width(&label, 1)),
+                height.saturating_sub(10 + (index as u32 + 1) * (text_height(1) + 4)),
+                &label,
+                1,
+                colour,
+            );
+        }
+
+        graphicimage
+    }
+}
+
+impl Data {
+    // The true wind angle, folded onto 0-180, and boat speed of every datapoint with a true
+    // wind speed from `tws_low` up to but not including `tws_high`: one slice of the polar.
+    pub fn twa_curve(&self, tws_low: f32, tws_high: f32) -> Vec<(f32, f32)> {
+        self.data
+            .iter()
+            .filter(|a| a.windspeed.is_some_and(|b| b >= tws_low && b < tws_high))
+            .filter_map(|a| Some((fold_angle(a.winddirection?), a.boatspeed?)))
+            .collect()
+    }
+
+    // Boat speed against true wind angle for one band of true wind speeds, as a scatter of
+    // every datapoint with a line through the median of each sector, smoothed over its
+    // neighbours.
+    pub fn twa_curve_image(&self, width: u32, height: u32, tws_low: f32, tws_high: f32) -> Pixels {
+        let mut graphicimage = Pixels::new(width, height);
+        let (plot_width, plot_height) = (
+            width.saturating_sub(2 * POLAR_MARGIN),
+            height.saturating_sub(2 * POLAR_MARGIN),
+        );
+        if plot_width < 2 || plot_height < 2 {
+            return graphicimage;
+        }
+        let points = self.twa_curve(tws_low, tws_high);
+        let largest_speed = points.iter().map(|a| a.1).fold(0f32, f32::max);
+        let ring_step = ((largest_speed.floor() + 1.) / 8.).ceil().max(1.) as u32;
+        let top_speed = (largest_speed.floor() as u32 + 1).div_ceil(ring_step) * ring_step;
+        let to_xy = |twa: f32, speed: f32| -> (u32, u32) {
+            (
+                POLAR_MARGIN + ((twa / 180.) * (plot_width - 1) as f32) as u32,
+                POLAR_MARGIN
+                    + ((speed / top_speed as f32) * (plot_height - 1) as f32)
+                        .min((plot_height - 1) as f32) as u32,
+            )
+        };
+
+        for speed in (0..=top_speed).step_by(ring_step as usize) {
+            let (left, y) = to_xy(0., speed as f32);
+            graphicimage.line_from_to((left, y), to_xy(180., speed as f32), GRID_COLOUR);
+            let label = speed.to_string();
+            draw_text(
+                &mut graphicimage,
+                left.saturating_sub(text_width(&label, 1) + 4),
+                y.saturating_sub(text_height(1) / 2),
+                &label,
+                1,
+                GRID_COLOUR,
+            );
+        }
+        for angle in (0..=180).step_by(30) {
+            let (x, bottom) = to_xy(angle as f32, 0.);
+            graphicimage.line_from_to(
+                (x, bottom),
+                to_xy(angle as f32, top_speed as f32),
+                GRID_COLOUR,
+            );
+            let label = angle.to_string();
+            draw_text(
+                &mut graphicimage,
+                x.saturating_sub(text_width(&label, 1) / 2),
+                bottom.saturating_sub(text_height(1) + 6),
+                &label,
+                1,
+                GRID_COLOUR,
+            );
+        }
+
+        let colour = BAND_COLOURS[2];
+        let faint = blend(colour, Rgb8Pixel { r: 0, g: 0, b: 0 }, SCATTER_OPACITY);
+        for (twa, speed) in &points {
+            let xy = to_xy(*twa, *speed);
+            graphicimage.line_from_to(xy, xy, faint);
+        }
+
+        let sectors = (180. / POLAR_TWA_STEP).round() as usize + 1;
+        let mut speeds = vec![Vec::new(); sectors];
+        for (twa, speed) in &points {
+            speeds[((twa / POLAR_TWA_STEP).round() as usize).min(sectors - 1)].push(*speed);
+        }
+        let medians: Vec<Option<f32>> = speeds
+            .into_iter()
+            .map(|mut a| (!a.is_empty()).then(|| percentile(&mut a, 50.)))
+            .collect();
+        let mut previous = None;
+        for (sector, median) in medians.iter().enumerate() {
+            if median.is_none() {
+                continue;
+            }
+            let neighbours: Vec<f32> = medians[sector.saturating_sub(CURVE_SMOOTHING_SECTORS)
+                ..(sector + CURVE_SMOOTHING_SECTORS + 1).min(sectors)]
+                .iter()
+                .flatten()
+                .copied()
+                .collect();
+            let smoothed = neighbours.iter().sum::<f32>() / neighbours.len() as f32;
+            let next = to_xy(sector as f32 * POLAR_TWA_STEP, smoothed);
+            if let Some(previous) = previous {
+                graphicimage.line_from_to(previous, next, colour);
+            }
+            previous = Some(next);
+        }
+
+        let label = format!("{tws_low} - {tws_high} KT");
+        draw_text(
+            &mut graphicimage,
+            width.saturating_sub(POLAR_MARGIN + text_width(&label, 1)),
+            height.saturating_sub(POLAR_MARGIN / 2 + text_height(1)),
+            &label,
+            1,
+            colour,
+        );
+
+        graphicimage
+    }
+}
+
+pub(crate) fn nearest_band(tws_bands: &[f32], windspeed: f32) -> usize {
+    tws_bands
+        .iter()
+        .enumerate()
+        .min_by(|a, b| (a.1 - windspeed).abs().total_cmp(&(b.1 - windspeed).abs()))
+        .map(|a| a.0)
+        .unwrap_or(0)
+}
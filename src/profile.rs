@@ -0,0 +1,36 @@
+use std::time::{Duration, Instant};
+
+// Time spent in each stage of loading and starting up, reported by `--profile`.
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    pub stages: Vec<(String, Duration)>,
+}
+
+impl Profile {
+    pub fn record(&mut self, stage: &str, duration: Duration) {
+        match self.stages.iter_mut().find(|a| a.0 == stage) {
+            Some(entry) => entry.1 += duration,
+            None => self.stages.push((stage.to_string(), duration)),
+        }
+    }
+
+    pub fn time<T>(&mut self, stage: &str, f: impl FnOnce() -> T) -> T {
+        let started = Instant::now();
+        let result = f();
+        self.record(stage, started.elapsed());
+        result
+    }
+
+    pub fn report(&self) {
+        let total: Duration = self.stages.iter().map(|a| a.1).sum();
+        println!("Profile:");
+        for (stage, duration) in &self.stages {
+            println!(
+                "  {stage:<10} {:>10.3} ms {:>5.1}%",
+                duration.as_secs_f64() * 1000.,
+                duration.as_secs_f64() * 100. / total.as_secs_f64().max(f64::EPSILON)
+            );
+        }
+        println!("  {:<10} {:>10.3} ms", "total", total.as_secs_f64() * 1000.);
+    }
+}
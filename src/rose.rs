@@ -0,0 +1,146 @@
+use crate::canvas::{Canvas, Rgb8Pixel};
+use crate::datapoints::Data;
+use crate::export::Pixels;
+use crate::polar::{nearest_band, BAND_COLOURS, DEFAULT_TWS_BANDS};
+use crate::text::{draw_text, text_height, text_width};
+
+pub const WIND_ROSE_SIZE: u32 = 400;
+pub const DEFAULT_WIND_ROSE_SECTORS: usize = 16;
+
+const ROSE_MARGIN: u32 = 30;
+// Degrees left empty either side of each wedge so that neighbouring sectors stay apart.
+const SECTOR_GAP: f32 = 1.;
+const GRID_COLOUR: Rgb8Pixel = Rgb8Pixel {
+    r: 0x60,
+    g: 0x60,
+    b: 0x60,
+};
+
+impl Data {
+    // The share of the datapoints, as a percentage, with the true wind from each compass
+    // sector, indexed [sector][band] where sector n is centred on n * 360 / sectors degrees.
+    // Datapoints stand in for time, as they do in the summary, and each counts towards the band
+    // nearest its true wind speed.
+    pub fn wind_rose_cells(&self, sectors: usize, tws_bands: &[f32]) -> Vec<Vec<f32>> {
+        let sectors = sectors.max(1);
+        let mut counts = vec![vec![0usize; tws_bands.len().max(1)]; sectors];
+        let mut total = 0;
+        for point in &self.data {
+            let Some(direction) = point.true_wind_direction() else {
+                continue;
+            };
+            let Some(windspeed) = point.windspeed else {
+                continue;
+            };
+            let sector = (direction * sectors as f32 / 360.).round() as usize % sectors;
+            counts[sector][nearest_band(tws_bands, windspeed)] += 1;
+            total += 1;
+        }
+        counts
+            .into_iter()
+            .map(|sector| {
+                sector
+                    .into_iter()
+                    .map(|count| count as f32 * 100. / total.max(1) as f32)
+                    .collect()
+            })
+            .collect()
+    }
+
+    // How long the true wind blew from each direction, as a wedge per compass sector with north
+    // at the top. Each wedge is split by true wind speed band, lightest in the middle, so its
+    // length is the share of the whole time spent with the wind from that sector.
+    pub fn wind_rose(&self, sectors: usize) -> Pixels {
+        self.wind_rose_with_bands(sectors, &DEFAULT_TWS_BANDS)
+    }
+
+    pub fn wind_rose_with_bands(&self, sectors: usize, tws_bands: &[f32]) -> Pixels {
+        let (width, height) = (WIND_ROSE_SIZE, WIND_ROSE_SIZE);
+        let mut graphicimage = Pixels::new(width, height);
+        let radius = (width / 2).saturating_sub(ROSE_MARGIN) as f32;
+        let cells = self.wind_rose_cells(sectors, tws_bands);
+        let largest_share = cells
+            .iter()
+            .map(|a| a.iter().sum::<f32>())
+            .fold(0f32, f32::max);
+        let ring_step = ((largest_share / 4.).ceil() as u32).max(1);
+        let top_share = ((largest_share.ceil() as u32).div_ceil(ring_step) * ring_step).max(1);
+
+        let centre = (width as f32 / 2., height as f32 / 2.);
+        // Bearings go clockwise from north at the top.
+        let to_xy = |bearing: f32, distance: f32| -> (u32, u32) {
+            let radians = bearing.to_radians();
+            (
+                ((centre.0 + distance * radians.sin()).max(0.) as u32).min(width - 1),
+                ((centre.1 + distance * radians.cos()).max(0.) as u32).min(height - 1),
+            )
+        };
+        let share_distance = |share: f32| share * radius / top_share as f32;
+
+        let sector_width = 360. / cells.len() as f32;
+        for (sector, bands) in cells.iter().enumerate() {
+            let middle = sector as f32 * sector_width;
+            let (from, to) = (
+                middle - sector_width / 2. + SECTOR_GAP,
+                middle + sector_width / 2. - SECTOR_GAP,
+            );
+            let mut inner = 0.;
+            for (band, share) in bands.iter().enumerate() {
+                let outer = inner + share_distance(*share);
+                if outer - inner >= 0.5 {
+                    let colour = BAND_COLOURS[band % BAND_COLOURS.len()];
+                    // Enough radial lines that there are no gaps between them at the outer edge.
+                    let steps = ((to - from).to_radians() * outer).ceil().max(1.) as u32 * 2;
+                    for step in 0..=steps {
+                        let bearing = from + (to - from) * step as f32 / steps as f32;
+                        graphicimage.line_from_to(
+                            to_xy(bearing, inner),
+                            to_xy(bearing, outer),
+                            colour,
+                        );
+                    }
+                }
+                inner = outer;
+            }
+        }
+
+        // The grid goes over the wedges so that their lengths can be read off it.
+        for share in (ring_step..=top_share).step_by(ring_step as usize) {
+            let distance = share_distance(share as f32);
+            let mut previous = to_xy(0., distance);
+            for bearing in (5..=360).step_by(5) {
+                let next = to_xy(bearing as f32, distance);
+                graphicimage.line_from_to(previous, next, GRID_COLOUR);
+                previous = next;
+            }
+            let label = format!("{share}%");
+            let (x, y) = to_xy(45., distance);
+            draw_text(&mut graphicimage, x + 2, y, &label, 1, GRID_COLOUR);
+        }
+        for (bearing, label) in [(0., "N"), (90., "E"), (180., "S"), (270., "W")] {
+            let (x, y) = to_xy(bearing, radius + 14.);
+            draw_text(
+                &mut graphicimage,
+                x.saturating_sub(text_width(label, 1) / 2),
+                y.saturating_sub(text_height(1) / 2),
+                label,
+                1,
+                GRID_COLOUR,
+            );
+        }
+
+        for (index, band) in tws_bands.iter().enumerate() {
+            let label = format!("{band} KT");
+            draw_text(
+                &mut graphicimage,
+                width.saturating_sub(10 + text_width(&label, 1)),
+                height.saturating_sub(10 + (index as u32 + 1) * (text_height(1) + 4)),
+                &label,
+                1,
+                BAND_COLOURS[index % BAND_COLOURS.len()],
+            );
+        }
+
+        graphicimage
+    }
+}
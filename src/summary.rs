@@ -0,0 +1,182 @@
+use crate::datapoints::{Data, DataPoint};
+use crate::maneuvers::{detect_maneuvers, ManeuverKind, MINIMUM_MANEUVER_DWELL_SECONDS};
+use crate::polar::nearest_band;
+use chrono::{DateTime, TimeDelta, Utc};
+
+// Mean radius of the earth in nautical miles.
+const EARTH_RADIUS_NM: f64 = 3440.065;
+// Speeds aren't carried across gaps in the log longer than this, so that distance isn't counted
+// while the instruments were off.
+const MAX_DISTANCE_GAP_SECONDS: i64 = 60;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Range {
+    pub min: f32,
+    pub mean: f32,
+    pub max: f32,
+}
+
+impl Range {
+    // None if there are no values.
+    fn of(values: impl Iterator<Item = f32>) -> Option<Range> {
+        let mut count = 0;
+        let mut total = 0f64;
+        let mut min = f32::INFINITY;
+        let mut max = f32::NEG_INFINITY;
+        for value in values {
+            count += 1;
+            total += value as f64;
+            min = min.min(value);
+            max = max.max(value);
+        }
+        (count > 0).then(|| Range {
+            min,
+            mean: (total / count as f64) as f32,
+            max,
+        })
+    }
+}
+
+// An overview of a session, for deciding whether it's worth a closer look.
+#[derive(Debug, Clone)]
+pub struct Summary {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub datapoints: usize,
+    pub boatspeed: Option<Range>,
+    pub windspeed: Option<Range>,
+    // Each true wind speed band with the percentage of datapoints that fall nearest to it.
+    pub tws_bands: Vec<(f32, f32)>,
+    // Nautical miles.
+    pub distance_sailed: f64,
+    pub distance_made_good: Option<f64>,
+}
+
+impl Summary {
+    pub fn duration(&self) -> TimeDelta {
+        self.end - self.start
+    }
+}
+
+// The tacks, gybes and distances of data that's still growing, brought up to date by going over
+// only the datapoints appended since the last update. Expects the data to be sorted.
+#[derive(Debug, Clone, Default)]
+pub struct RunningTotals {
+    pub tacks: usize,
+    pub gybes: usize,
+    // Nautical miles.
+    pub distance_sailed: f64,
+    pub distance_made_good: Option<f64>,
+    first_position: Option<(f64, f64)>,
+    // How many datapoints have been counted.
+    counted: usize,
+    // Where the latest tack or gybe starts. Whether the next one counts depends on the tack
+    // before it, so maneuvers are looked for again from there.
+    maneuvers_from: usize,
+}
+
+impl RunningTotals {
+    pub fn new(data: &Data) -> RunningTotals {
+        let mut totals = RunningTotals::default();
+        totals.update(data);
+        totals
+    }
+
+    pub fn update(&mut self, data: &Data) {
+        let data = &data.data;
+        let appended = &data[self.counted.min(data.len())..];
+        self.distance_sailed += data[self.counted.saturating_sub(1).min(data.len())..]
+            .windows(2)
+            .filter_map(|pair| distance(&pair[0], &pair[1]))
+            .sum::<f64>();
+        let mut positions = appended.iter().filter_map(|a| a.latitude.zip(a.longitude));
+        if let Some(next) = positions.next() {
+            let last = positions.last().unwrap_or(next);
+            let first = *self.first_position.get_or_insert(next);
+            self.distance_made_good = Some(great_circle_distance(first, last));
+        }
+        self.counted = data.len();
+
+        let maneuvers = detect_maneuvers(
+            &data[self.maneuvers_from..],
+            TimeDelta::seconds(MINIMUM_MANEUVER_DWELL_SECONDS),
+        );
+        let tacks = maneuvers
+            .iter()
+            .filter(|a| a.kind == ManeuverKind::Tack)
+            .count();
+        self.tacks += tacks;
+        self.gybes += maneuvers.len() - tacks;
+        if let Some(last) = maneuvers.last() {
+            self.maneuvers_from +=
+                data[self.maneuvers_from..].partition_point(|a| a.timestamp < last.timestamp);
+        }
+    }
+}
+
+impl Data {
+    // None if there are no datapoints.
+    pub fn summary(&self, tws_bands: &[f32]) -> Option<Summary> {
+        let (start, end) = self.time_range()?;
+        let mut band_counts = vec![0usize; tws_bands.len()];
+        let mut with_wind = 0;
+        for windspeed in self.data.iter().filter_map(|a| a.windspeed) {
+            with_wind += 1;
+            if !tws_bands.is_empty() {
+                band_counts[nearest_band(tws_bands, windspeed)] += 1;
+            }
+        }
+        Some(Summary {
+            start,
+            end,
+            datapoints: self.data.len(),
+            boatspeed: Range::of(self.data.iter().filter_map(|a| a.boatspeed)),
+            windspeed: Range::of(self.data.iter().filter_map(|a| a.windspeed)),
+            tws_bands: tws_bands
+                .iter()
+                .zip(band_counts)
+                .map(|(band, count)| (*band, count as f32 * 100. / with_wind.max(1) as f32))
+                .collect(),
+            distance_sailed: self.distance_sailed(),
+            distance_made_good: self.distance_made_good(),
+        })
+    }
+
+    // Nautical miles travelled, from the speed over the ground where there is one and the speed
+    // through the water otherwise, each held until the next datapoint.
+    pub fn distance_sailed(&self) -> f64 {
+        self.data
+            .windows(2)
+            .filter_map(|pair| distance(&pair[0], &pair[1]))
+            .sum()
+    }
+
+    // Nautical miles in a straight line from the first logged position to the last, or None
+    // without positions.
+    pub fn distance_made_good(&self) -> Option<f64> {
+        let mut positions = self.data.iter().filter_map(|a| a.latitude.zip(a.longitude));
+        let first = positions.next()?;
+        let last = positions.last().unwrap_or(first);
+        Some(great_circle_distance(first, last))
+    }
+}
+
+// Nautical miles from one datapoint to the next at the first one's speed, or None across a gap.
+fn distance(from: &DataPoint, to: &DataPoint) -> Option<f64> {
+    let milliseconds = (to.timestamp - from.timestamp).num_milliseconds();
+    if milliseconds > MAX_DISTANCE_GAP_SECONDS * 1000 {
+        return None;
+    }
+    let speed = from.sog.or(from.boatspeed)?;
+    Some(speed as f64 * milliseconds as f64 / 3_600_000.)
+}
+
+// Haversine distance in nautical miles between two (latitude, longitude) positions in degrees.
+fn great_circle_distance(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (latitude1, latitude2) = (from.0.to_radians(), to.0.to_radians());
+    let half_latitude = (latitude2 - latitude1) / 2.;
+    let half_longitude = (to.1 - from.1).to_radians() / 2.;
+    let a = half_latitude.sin().powi(2)
+        + latitude1.cos() * latitude2.cos() * half_longitude.sin().powi(2);
+    2. * EARTH_RADIUS_NM * a.sqrt().min(1.).asin()
+}
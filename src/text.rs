@@ -0,0 +1,111 @@
+use crate::canvas::{Canvas, Rgb8Pixel};
+
+// Glyphs are drawn as strokes on a grid this size, with y increasing upwards from the baseline.
+pub const GLYPH_WIDTH: u32 = 4;
+pub const GLYPH_HEIGHT: u32 = 6;
+// Horizontal distance from the start of one character to the next.
+pub const GLYPH_ADVANCE: u32 = GLYPH_WIDTH + 2;
+
+type Stroke = &'static [(u32, u32)];
+
+const BOX: &[Stroke] = &[&[(0, 0), (4, 0), (4, 6), (0, 6), (0, 0)]];
+
+fn glyph(c: char) -> &'static [Stroke] {
+    match c.to_ascii_uppercase() {
+        '0' | 'O' => BOX,
+        '1' => &[&[(1, 5), (2, 6), (2, 0)], &[(1, 0), (3, 0)]],
+        '2' => &[&[(0, 6), (4, 6), (4, 3), (0, 3), (0, 0), (4, 0)]],
+        '3' => &[&[(0, 6), (4, 6), (4, 0), (0, 0)], &[(1, 3), (4, 3)]],
+        '4' => &[&[(0, 6), (0, 3), (4, 3)], &[(4, 6), (4, 0)]],
+        '5' | 'S' => &[&[(4, 6), (0, 6), (0, 3), (4, 3), (4, 0), (0, 0)]],
+        '6' => &[&[(4, 6), (0, 6), (0, 0), (4, 0), (4, 3), (0, 3)]],
+        '7' => &[&[(0, 6), (4, 6), (2, 0)]],
+        '8' => &[&[(0, 0), (4, 0), (4, 6), (0, 6), (0, 0)], &[(0, 3), (4, 3)]],
+        '9' => &[&[(4, 3), (0, 3), (0, 6), (4, 6), (4, 0), (0, 0)]],
+        'A' => &[&[(0, 0), (0, 4), (2, 6), (4, 4), (4, 0)], &[(0, 3), (4, 3)]],
+        'B' => &[
+            &[(0, 0), (0, 6), (3, 6), (4, 5), (4, 4), (3, 3), (0, 3)],
+            &[(3, 3), (4, 2), (4, 1), (3, 0), (0, 0)],
+        ],
+        'C' => &[&[(4, 6), (0, 6), (0, 0), (4, 0)]],
+        'D' => &[&[(0, 0), (0, 6), (2, 6), (4, 4), (4, 2), (2, 0), (0, 0)]],
+        'E' => &[&[(4, 6), (0, 6), (0, 0), (4, 0)], &[(0, 3), (3, 3)]],
+        'F' => &[&[(4, 6), (0, 6), (0, 0)], &[(0, 3), (3, 3)]],
+        'G' => &[&[(4, 6), (0, 6), (0, 0), (4, 0), (4, 3), (2, 3)]],
+        'H' => &[&[(0, 0), (0, 6)], &[(4, 0), (4, 6)], &[(0, 3), (4, 3)]],
+        'I' => &[&[(1, 6), (3, 6)], &[(2, 6), (2, 0)], &[(1, 0), (3, 0)]],
+        'J' => &[&[(4, 6), (4, 0), (0, 0), (0, 2)]],
+        'K' => &[&[(0, 0), (0, 6)], &[(4, 6), (0, 3), (4, 0)]],
+        'L' => &[&[(0, 6), (0, 0), (4, 0)]],
+        'M' => &[&[(0, 0), (0, 6), (2, 3), (4, 6), (4, 0)]],
+        'N' => &[&[(0, 0), (0, 6), (4, 0), (4, 6)]],
+        'P' => &[&[(0, 0), (0, 6), (4, 6), (4, 3), (0, 3)]],
+        'Q' => &[&[(0, 0), (4, 0), (4, 6), (0, 6), (0, 0)], &[(2, 2), (4, 0)]],
+        'R' => &[&[(0, 0), (0, 6), (4, 6), (4, 3), (0, 3)], &[(1, 3), (4, 0)]],
+        'T' => &[&[(0, 6), (4, 6)], &[(2, 6), (2, 0)]],
+        'U' => &[&[(0, 6), (0, 0), (4, 0), (4, 6)]],
+        'V' => &[&[(0, 6), (2, 0), (4, 6)]],
+        'W' => &[&[(0, 6), (0, 0), (2, 3), (4, 0), (4, 6)]],
+        'X' => &[&[(0, 0), (4, 6)], &[(0, 6), (4, 0)]],
+        'Y' => &[&[(0, 6), (2, 3), (4, 6)], &[(2, 3), (2, 0)]],
+        'Z' => &[&[(0, 6), (4, 6), (0, 0), (4, 0)]],
+        ':' => &[&[(2, 1), (2, 2)], &[(2, 4), (2, 5)]],
+        '-' => &[&[(1, 3), (3, 3)]],
+        '.' => &[&[(2, 0), (2, 1)]],
+        '/' => &[&[(0, 0), (4, 6)]],
+        '+' => &[&[(0, 3), (4, 3)], &[(2, 1), (2, 5)]],
+        '%' => &[&[(0, 0), (4, 6)], &[(0, 5), (0, 6)], &[(4, 0), (4, 1)]],
+        '(' => &[&[(3, 6), (2, 5), (2, 1), (3, 0)]],
+        ')' => &[&[(1, 6), (2, 5), (2, 1), (1, 0)]],
+        _ => &[],
+    }
+}
+
+// Width in pixels of `text` drawn at `scale`, without the trailing gap after the last character.
+pub fn text_width(text: &str, scale: u32) -> u32 {
+    match text.chars().count() as u32 {
+        0 => 0,
+        count => ((count - 1) * GLYPH_ADVANCE + GLYPH_WIDTH) * scale,
+    }
+}
+
+pub fn text_height(scale: u32) -> u32 {
+    GLYPH_HEIGHT * scale
+}
+
+// Draws `text` with the bottom left corner of its first character at (x, y), in whatever text
+// `image` has.
+pub fn draw_text(
+    image: &mut impl Canvas,
+    x: u32,
+    y: u32,
+    text: &str,
+    scale: u32,
+    colour: Rgb8Pixel,
+) {
+    image.text(x, y, text, scale, colour);
+}
+
+// Draws `text` out of lines, as `draw_text` does on a canvas without text of its own. Characters
+// without a glyph are left blank.
+pub fn stroke_text<C: Canvas + ?Sized>(
+    image: &mut C,
+    x: u32,
+    y: u32,
+    text: &str,
+    scale: u32,
+    colour: Rgb8Pixel,
+) {
+    for (index, c) in text.chars().enumerate() {
+        let left = x + index as u32 * GLYPH_ADVANCE * scale;
+        for stroke in glyph(c) {
+            for segment in stroke.windows(2) {
+                image.line_from_to(
+                    (left + segment[0].0 * scale, y + segment[0].1 * scale),
+                    (left + segment[1].0 * scale, y + segment[1].1 * scale),
+                    colour,
+                );
+            }
+        }
+    }
+}
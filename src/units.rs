@@ -0,0 +1,57 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+
+// Speeds are stored and computed in knots throughout, and only converted to one of these when
+// they're shown or written out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SpeedUnit {
+    #[default]
+    Knots,
+    KilometresPerHour,
+    MilesPerHour,
+    MetresPerSecond,
+}
+
+impl SpeedUnit {
+    // In the order they're offered in the UI.
+    pub const ALL: [SpeedUnit; 4] = [
+        SpeedUnit::Knots,
+        SpeedUnit::KilometresPerHour,
+        SpeedUnit::MilesPerHour,
+        SpeedUnit::MetresPerSecond,
+    ];
+
+    // How many of this unit make one knot.
+    pub const fn per_knot(&self) -> f32 {
+        match self {
+            SpeedUnit::Knots => 1.,
+            SpeedUnit::KilometresPerHour => 1.852,
+            SpeedUnit::MilesPerHour => 1852. / 1609.344,
+            SpeedUnit::MetresPerSecond => 1852. / 3600.,
+        }
+    }
+
+    pub fn from_knots(&self, knots: f32) -> f32 {
+        knots * self.per_knot()
+    }
+
+    pub fn to_knots(&self, value: f32) -> f32 {
+        value / self.per_knot()
+    }
+
+    pub fn abbreviation(&self) -> &'static str {
+        match self {
+            SpeedUnit::Knots => "kn",
+            SpeedUnit::KilometresPerHour => "km/h",
+            SpeedUnit::MilesPerHour => "mph",
+            SpeedUnit::MetresPerSecond => "m/s",
+        }
+    }
+
+    pub fn from_abbreviation(abbreviation: &str) -> Option<SpeedUnit> {
+        SpeedUnit::ALL
+            .into_iter()
+            .find(|a| a.abbreviation() == abbreviation)
+    }
+}
@@ -0,0 +1,160 @@
+use crate::datapoints::Data;
+use crate::export::Pixels;
+use crate::graph::GraphOptions;
+use crate::summary::RunningTotals;
+use chrono::{DateTime, TimeDelta, Utc};
+use log::debug;
+
+// Span given to a data set whose points all share a single timestamp, and the shortest span
+// that can be zoomed in to.
+pub const MINIMUM_TIME_SPAN_SECONDS: i64 = 1;
+
+// Whether a view ending at `end` follows the latest data, at `latest`, as it arrives. Moving
+// the view back from the end pauses following, and moving it back to the end resumes it.
+pub fn is_following<T: PartialOrd>(end: T, latest: T) -> bool {
+    end >= latest
+}
+
+// The scroller offsets for the same span moved up to the latest data, at `max_offset`, as the
+// Live button does.
+pub fn live_offsets(start_offset: f32, end_offset: f32, max_offset: f32) -> (f32, f32) {
+    let span = end_offset - start_offset;
+    ((max_offset - span).max(0.), max_offset)
+}
+
+// The first and last timestamps of the data, for the scrollers to cover. With no span at all the
+// scrollers would have nowhere to go, so the range is padded a little.
+pub fn padded_range(range: (DateTime<Utc>, DateTime<Utc>)) -> (DateTime<Utc>, DateTime<Utc>) {
+    let (min, max) = range;
+    if max == min {
+        debug!("All datapoints share a single timestamp.");
+        (min, min + TimeDelta::seconds(MINIMUM_TIME_SPAN_SECONDS))
+    } else {
+        (min, max)
+    }
+}
+
+// Everything that determines what the graph shows, shared by the on-screen render and exports
+// so that an export matches the view exactly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewState {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub options: GraphOptions,
+    pub despike: bool,
+}
+
+impl ViewState {
+    pub fn data<'a>(&self, datasets: &'a Datasets) -> &'a Data {
+        if self.despike {
+            &datasets.despiked
+        } else {
+            &datasets.raw
+        }
+    }
+
+    pub fn render(&self, datasets: &Datasets, width: u32, height: u32) -> Pixels {
+        self.data(datasets).graph_with_comparison(
+            datasets.comparison.as_ref(),
+            width,
+            height,
+            self.start,
+            self.end,
+            &self.options,
+        )
+    }
+
+    // The view as it's shown at `width` by `height`, drawn `scale` times the size for saving.
+    pub fn export(&self, datasets: &Datasets, width: u32, height: u32, scale: u32) -> Pixels {
+        self.render(datasets, width * scale, height * scale)
+    }
+}
+
+// The data as loaded and with speed spikes removed, so that despiking can be switched on and off
+// without reloading, and another log to compare them with, moved to start at the same time.
+pub struct Datasets {
+    pub raw: Data,
+    pub despiked: Data,
+    pub comparison: Option<Data>,
+    pub totals: RunningTotals,
+    // Counts every change to the data, so that a graph drawn from it can tell it's out of date
+    // even when the number of datapoints hasn't changed.
+    generation: u64,
+}
+
+impl Datasets {
+    pub fn new(raw: Data, despike_threshold: f32, comparison: Option<Data>) -> Datasets {
+        let mut despiked = raw.clone();
+        despiked.despike(despike_threshold);
+        let comparison = comparison.map(|a| a.aligned_to(&raw));
+        Datasets {
+            totals: RunningTotals::new(&raw),
+            raw,
+            despiked,
+            comparison,
+            generation: 0,
+        }
+    }
+
+    // Replaces the log with another one, keeping the comparison but lined up with the new log.
+    pub fn replace_raw(&mut self, raw: Data, despike_threshold: f32) {
+        let comparison = self.comparison.take();
+        *self = Datasets {
+            generation: self.generation + 1,
+            ..Datasets::new(raw, despike_threshold, comparison)
+        };
+    }
+
+    // Brings the despiked data and the totals up to date after datapoints are appended to
+    // `raw`, going over only the new ones.
+    pub fn raw_changed(&mut self, despike_threshold: f32) {
+        self.despiked.despike_appended(&self.raw, despike_threshold);
+        self.totals.update(&self.raw);
+        self.generation += 1;
+    }
+
+    pub fn set_comparison(&mut self, comparison: Option<Data>) {
+        self.comparison = comparison.map(|a| a.aligned_to(&self.raw));
+        self.generation += 1;
+    }
+
+    pub fn range(&self) -> (DateTime<Utc>, DateTime<Utc>) {
+        padded_range(self.raw.display_range())
+    }
+}
+
+// The last graph drawn and everything it was drawn from. Redraws that don't change any of that,
+// such as pressing enter in a time field or releasing a scroller without moving it, reuse it
+// instead of binning the window again. It's kept as whatever the pixels were turned into, such
+// as the window's image.
+pub struct GraphCache<T> {
+    // The view, image size and generation of the data.
+    last: Option<((ViewState, u32, u32, u64), T)>,
+}
+
+impl<T> Default for GraphCache<T> {
+    fn default() -> Self {
+        GraphCache { last: None }
+    }
+}
+
+impl<T: Clone> GraphCache<T> {
+    pub fn render(
+        &mut self,
+        view: &ViewState,
+        datasets: &Datasets,
+        width: u32,
+        height: u32,
+        convert: impl FnOnce(Pixels) -> T,
+    ) -> T {
+        let key = (view.clone(), width, height, datasets.generation);
+        match &self.last {
+            Some((last_key, image)) if *last_key == key => image.clone(),
+            _ => {
+                let image = convert(view.render(datasets, width, height));
+                self.last = Some((key, image.clone()));
+                image
+            }
+        }
+    }
+}
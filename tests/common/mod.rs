@@ -0,0 +1,59 @@
+// Shared by the integration tests, each of which only uses some of it.
+#![allow(dead_code)]
+
+use chrono::{DateTime, TimeDelta, Utc};
+use make_polar_rs::datapoints::{Data, DataPoint};
+
+pub fn start() -> DateTime<Utc> {
+    "2024-06-05T00:00:00Z".parse().unwrap()
+}
+
+// A datapoint `seconds` after `start`, at `boatspeed` in 10 knots of wind at 45 degrees.
+pub fn point(seconds: i64, boatspeed: f32) -> DataPoint {
+    DataPoint {
+        timestamp: start() + TimeDelta::seconds(seconds),
+        boatspeed: Some(boatspeed),
+        windspeed: Some(10.),
+        winddirection: Some(45.),
+        ..DataPoint::new()
+    }
+}
+
+// A datapoint every second for `seconds`, all at `boatspeed`.
+pub fn steady(seconds: i64, boatspeed: f32) -> Data {
+    Data::from_datapoints((0..seconds).map(|a| point(a, boatspeed)).collect())
+}
+
+// A datapoint every five degrees from 30 to 180 off the bow, all at `boatspeed` in 10 knots of
+// wind.
+pub fn sweep(boatspeed: f32) -> Data {
+    Data::from_datapoints(
+        (6..=36)
+            .map(|step| DataPoint {
+                timestamp: start() + TimeDelta::seconds(step),
+                boatspeed: Some(boatspeed),
+                windspeed: Some(10.),
+                winddirection: Some(step as f32 * 5.),
+                ..DataPoint::new()
+            })
+            .collect(),
+    )
+}
+
+pub fn with_checksum(body: &str) -> String {
+    let checksum = body.bytes().fold(0u8, |a, b| a ^ b);
+    format!("${body}*{checksum:02X}\n")
+}
+
+// `seconds` of the sentences `bodies` gives for each second, after a ZDA for it.
+pub fn log(seconds: i64, bodies: impl Fn(i64) -> Vec<String>) -> String {
+    (0..seconds)
+        .flat_map(|second| {
+            let time = start() + TimeDelta::seconds(second);
+            let mut sentences = vec![format!("GPZDA,{},05,06,2024,,", time.format("%H%M%S.00"))];
+            sentences.extend(bodies(second));
+            sentences
+        })
+        .map(|a| with_checksum(&a))
+        .collect()
+}
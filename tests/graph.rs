@@ -0,0 +1,319 @@
+mod common;
+
+use chrono::TimeDelta;
+use common::{point, start, steady};
+use make_polar_rs::channels::Channel;
+use make_polar_rs::datapoints::{Data, DataPoint};
+use make_polar_rs::export::Pixels;
+use make_polar_rs::graph::{
+    graph_plot_width, GraphOptions, LegendPosition, Theme, SPEED_AXIS_WIDTH,
+};
+use make_polar_rs::units::SpeedUnit;
+use make_polar_rs::view::{Datasets, ViewState};
+
+const WIDTH: u32 = 200;
+const HEIGHT: u32 = 120;
+
+// With nothing drawn over the plot that could land on the pixels being checked.
+fn options() -> GraphOptions {
+    GraphOptions {
+        legend: LegendPosition::Hidden,
+        maneuvers: false,
+        ..GraphOptions::default()
+    }
+}
+
+fn render(data: &Data, options: &GraphOptions) -> Pixels {
+    data.graph(
+        WIDTH,
+        HEIGHT,
+        start(),
+        start() + TimeDelta::seconds(60),
+        options,
+    )
+}
+
+// The columns of the plot containing `options`' boat speed colour, and the topmost row it's in
+// for each. The speed axis labels to the left of the plot are drawn in the same colour.
+fn boat_speed_columns(pixels: &Pixels, options: &GraphOptions) -> Vec<(u32, u32)> {
+    let colour = options.colour(Channel::BoatSpeed);
+    (SPEED_AXIS_WIDTH..pixels.width)
+        .filter_map(|x| {
+            (0..pixels.height)
+                .find(|y| pixels.get(x, *y) == Some(colour))
+                .map(|y| (x, y))
+        })
+        .collect()
+}
+
+// Only boat speed, for the tests that look for its trace.
+fn boat_speed_options() -> GraphOptions {
+    GraphOptions {
+        channels: vec![Channel::BoatSpeed],
+        ..options()
+    }
+}
+
+#[test]
+fn empty_data_says_so() {
+    let options = boat_speed_options();
+    let pixels = render(&Data::new(), &options);
+    assert_eq!((pixels.width, pixels.height), (WIDTH, HEIGHT));
+    assert!(boat_speed_columns(&pixels, &options).is_empty());
+    assert!(pixels.pixels.contains(&options.theme.axis()));
+}
+
+#[test]
+fn single_point_says_there_is_no_data() {
+    let options = boat_speed_options();
+    let pixels = render(&Data::from_datapoints(vec![point(30, 5.)]), &options);
+    assert_eq!((pixels.width, pixels.height), (WIDTH, HEIGHT));
+    assert!(boat_speed_columns(&pixels, &options).is_empty());
+    assert!(pixels.pixels.contains(&options.theme.axis()));
+}
+
+#[test]
+fn two_points_are_drawn_at_either_end() {
+    let options = boat_speed_options();
+    let pixels = render(
+        &Data::from_datapoints(vec![point(0, 5.), point(60, 5.)]),
+        &options,
+    );
+    let columns = boat_speed_columns(&pixels, &options);
+    assert!(columns.len() >= 2, "{columns:?}");
+    let (first, last) = (columns[0], columns[columns.len() - 1]);
+    assert_eq!(first.0, SPEED_AXIS_WIDTH);
+    assert!(
+        last.0 - first.0 >= graph_plot_width(WIDTH) - 2,
+        "{columns:?}"
+    );
+    // The same speed is at the same height.
+    assert_eq!(first.1, last.1);
+}
+
+#[test]
+fn faster_is_higher() {
+    let options = boat_speed_options();
+    let pixels = render(
+        &Data::from_datapoints(vec![point(0, 2.), point(60, 6.)]),
+        &options,
+    );
+    let columns = boat_speed_columns(&pixels, &options);
+    assert!(columns.len() >= 2, "{columns:?}");
+    // Rows count down from the top of the image.
+    assert!(columns[columns.len() - 1].1 < columns[0].1, "{columns:?}");
+}
+
+#[test]
+fn light_theme_fills_the_background() {
+    let options = GraphOptions {
+        theme: Theme::Light,
+        ..boat_speed_options()
+    };
+    let pixels = render(
+        &Data::from_datapoints(vec![point(0, 5.), point(60, 5.)]),
+        &options,
+    );
+    assert_eq!(pixels.get(0, 0), Some(Theme::Light.background()));
+    assert_eq!(pixels.get(WIDTH - 1, 0), Some(Theme::Light.background()));
+}
+
+#[test]
+fn percentile_scale_keeps_a_lone_spike_from_squashing_the_trace() {
+    let mut points: Vec<DataPoint> = (0..=60).map(|a| point(a, 5.)).collect();
+    points[30].boatspeed = Some(40.);
+    let data = Data::from_datapoints(points);
+    // The row of the steady 5 knots at the left of the graph, and the topmost row of the trace
+    // anywhere.
+    let rows = |options: &GraphOptions| {
+        let columns = boat_speed_columns(&render(&data, options), options);
+        let top = columns.iter().map(|a| a.1).min().unwrap();
+        (columns[0].1, top)
+    };
+    let (absolute, _) = rows(&options());
+    let (scaled, spike) = rows(&GraphOptions {
+        scale_percentile: Some(99.),
+        ..options()
+    });
+    // Rows count down from the top of the image.
+    assert!(scaled + HEIGHT / 8 < absolute, "{scaled} {absolute}");
+    // The spike is still there, clipped to the top of the graph.
+    assert!(spike < scaled, "{spike} {scaled}");
+}
+
+#[test]
+fn percentile_scale_leaves_out_readings_that_arent_numbers() {
+    let options = GraphOptions {
+        scale_percentile: Some(99.),
+        ..options()
+    };
+    let steady = steady(61, 5.);
+    let mut points: Vec<DataPoint> = (0..=60).map(|a| point(a, 5.)).collect();
+    points[30].boatspeed = Some(f32::NAN);
+    let data = Data::from_datapoints(points);
+    let left = |data: &Data| boat_speed_columns(&render(data, &options), &options)[0];
+    assert_eq!(left(&data), left(&steady));
+}
+
+#[test]
+fn night_is_shaded_before_sunrise_and_after_sunset() {
+    // A day on the equator at the Greenwich meridian, where the sun rises and sets close to
+    // 06:00 and 18:00 UTC.
+    let points = (0..=24 * 60)
+        .map(|a| DataPoint {
+            latitude: Some(0.),
+            longitude: Some(0.),
+            ..point(a * 60, 5.)
+        })
+        .collect();
+    let data = Data::from_datapoints(points);
+    let options = GraphOptions {
+        daynight: true,
+        ..options()
+    };
+    let pixels = data.graph(
+        WIDTH,
+        HEIGHT,
+        start(),
+        start() + TimeDelta::days(1),
+        &options,
+    );
+    let plot_width = graph_plot_width(WIDTH);
+    let shaded: Vec<bool> = (0..plot_width)
+        .map(|x| pixels.column_contains(SPEED_AXIS_WIDTH + x, Theme::Dark.night()))
+        .collect();
+    let hour = |column: usize| column as f32 * 24. / plot_width as f32;
+    let sunrise = shaded.iter().position(|a| !a).unwrap();
+    let sunset = shaded.iter().rposition(|a| !a).unwrap();
+    assert!((hour(sunrise) - 6.).abs() < 0.5, "{shaded:?}");
+    assert!((hour(sunset) - 18.).abs() < 0.5, "{shaded:?}");
+    assert!(shaded[..sunrise].iter().all(|a| *a), "{shaded:?}");
+    assert!(!shaded[sunrise..=sunset].iter().any(|a| *a), "{shaded:?}");
+    assert!(shaded[sunset + 1..].iter().all(|a| *a), "{shaded:?}");
+}
+
+#[test]
+fn only_the_chosen_channels_are_drawn() {
+    use Channel::{BoatSpeed, Heading, WindDirection, WindSpeed};
+    let all = [BoatSpeed, WindSpeed, WindDirection, Heading];
+    // Each at a different height, so that none hides another.
+    let data = Data::from_datapoints(
+        (0..=60)
+            .map(|a| DataPoint {
+                heading: Some(270.),
+                ..point(a, 5.)
+            })
+            .collect(),
+    );
+    for channels in [
+        vec![BoatSpeed],
+        vec![WindSpeed],
+        vec![WindDirection, Heading],
+        vec![Heading, BoatSpeed],
+        all.to_vec(),
+    ] {
+        let pixels = render(
+            &data,
+            &GraphOptions {
+                channels: channels.clone(),
+                ..options()
+            },
+        );
+        for channel in all {
+            assert_eq!(
+                pixels.column_contains(WIDTH / 2, channel.colour()),
+                channels.contains(&channel),
+                "{channel:?} with {channels:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn derived_channel_is_drawn_and_exported() {
+    let data = steady(61, 5.).with_derived("double_speed", |a| a.boatspeed.map(|b| b * 2.));
+    let derived = data.derived_channel("double_speed").unwrap();
+    assert_eq!(derived, Channel::Derived(0));
+    assert_eq!(data.channel_name(derived), "double_speed");
+    assert_eq!(data.value(&data.data[0], derived), Some(10.));
+
+    let options = GraphOptions {
+        channels: vec![derived],
+        ..options()
+    };
+    let pixels = render(&data, &options);
+    assert!(pixels.column_contains(WIDTH / 2, derived.colour()));
+    assert!(!pixels.column_contains(WIDTH / 2, Channel::BoatSpeed.colour()));
+
+    let datasets = Datasets::new(data.clone(), 5., None);
+    let view = ViewState {
+        start: start(),
+        end: start() + TimeDelta::seconds(60),
+        options,
+        despike: false,
+    };
+    let exported = view.export(&datasets, WIDTH, HEIGHT, 2);
+    assert!(exported.column_contains(WIDTH, derived.colour()));
+
+    let csv = data.to_csv(SpeedUnit::Knots);
+    let mut lines = csv.lines();
+    assert!(lines.next().unwrap().ends_with(",double_speed"));
+    assert_eq!(lines.clone().count(), data.data.len());
+    assert!(lines.all(|a| a.ends_with(",10.00")), "{csv}");
+}
+
+#[test]
+fn points_sharing_a_timestamp_are_one_column() {
+    let options = boat_speed_options();
+    let data = Data::from_datapoints(vec![point(30, 5.), point(30, 5.)]);
+    for (start_datetime, end_datetime) in [
+        (start(), start() + TimeDelta::seconds(60)),
+        // Zoomed in as far as it goes.
+        (
+            start() + TimeDelta::seconds(30),
+            start() + TimeDelta::seconds(30),
+        ),
+    ] {
+        let pixels = data.graph(WIDTH, HEIGHT, start_datetime, end_datetime, &options);
+        let columns = boat_speed_columns(&pixels, &options);
+        assert_eq!(columns.len(), 1, "{columns:?}");
+        assert_eq!(columns[0].0, SPEED_AXIS_WIDTH);
+    }
+}
+
+#[test]
+fn line_width_thickens_the_trace() {
+    let data = Data::from_datapoints(vec![point(0, 5.), point(60, 5.)]);
+    for line_width in [1, 3] {
+        let options = GraphOptions {
+            line_width,
+            ..boat_speed_options()
+        };
+        let pixels = render(&data, &options);
+        let colour = options.colour(Channel::BoatSpeed);
+        let rows = (0..HEIGHT)
+            .filter(|y| pixels.get(SPEED_AXIS_WIDTH, *y) == Some(colour))
+            .count();
+        assert_eq!(rows, line_width as usize);
+    }
+}
+
+#[test]
+fn svg_draws_the_trace_and_labels() {
+    let options = options();
+    let svg = Data::from_datapoints(vec![point(0, 5.), point(60, 5.)]).graph_svg(
+        WIDTH,
+        HEIGHT,
+        start(),
+        start() + TimeDelta::seconds(60),
+        &options,
+    );
+    let colour = options.colour(Channel::BoatSpeed);
+    let stroke = format!(
+        "stroke=\"#{:02x}{:02x}{:02x}\"",
+        colour.r, colour.g, colour.b
+    );
+    assert!(svg.starts_with("<svg "), "{svg}");
+    assert!(svg.contains(&stroke), "{svg}");
+    assert!(svg.contains("<text "), "{svg}");
+}
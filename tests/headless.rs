@@ -0,0 +1,80 @@
+mod common;
+
+use common::log;
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+// Runs the program on a minute of boat and wind speeds from stdin with no display to open a
+// window on, as on a server.
+fn run_without_display(args: &[&str]) -> Output {
+    let log = log(60, |_| {
+        vec![
+            "VWVHW,,T,,M,5.0,N,9.3,K,".to_string(),
+            "WIMWV,45.0,T,12.0,N,A".to_string(),
+        ]
+    });
+    let mut child = Command::new(env!("CARGO_BIN_EXE_make-polar-rs"))
+        .args(args)
+        .env_remove("DISPLAY")
+        .env_remove("WAYLAND_DISPLAY")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(log.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn no_gui_reports_without_a_display() {
+    let output = run_without_display(&["--no-gui"]);
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("datapoints from 2024-06-05T00:00"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn profile_is_reported_after_the_statistics() {
+    let output = run_without_display(&["--no-gui", "--profile"]);
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let profile = stdout.find("Profile:").expect(&stdout);
+    assert!(
+        stdout.find("datapoints from").unwrap() < profile,
+        "{stdout}"
+    );
+    for stage in ["read", "parse", "assemble", "overview", "total"] {
+        assert!(
+            stdout[profile..].contains(&format!("  {stage} ")),
+            "{stdout}"
+        );
+    }
+}
+
+#[test]
+fn summary_runs_without_a_display() {
+    let output = run_without_display(&["--summary"]);
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Datapoints"), "{stdout}");
+    assert!(stdout.contains("Boat speed min 5.0"), "{stdout}");
+}
+
+#[test]
+fn graph_is_saved_without_a_display() {
+    let path = std::env::temp_dir().join(format!("make-polar-headless-{}.png", std::process::id()));
+    let output = run_without_display(&["--output", path.to_str().unwrap()]);
+    let written = std::fs::read(&path);
+    let _ = std::fs::remove_file(&path);
+    assert!(output.status.success(), "{output:?}");
+    assert!(written.unwrap().starts_with(b"\x89PNG"));
+}
@@ -0,0 +1,303 @@
+mod common;
+
+use chrono::TimeDelta;
+use common::{log, start};
+use make_polar_rs::canvas::Rgb8Pixel;
+use make_polar_rs::channels::Channel;
+use make_polar_rs::datapoints::{Data, DataPoint, LoadOptions};
+use make_polar_rs::graph::{GraphOptions, LegendPosition};
+use make_polar_rs::overview::{Overview, OVERVIEW_HEIGHT, OVERVIEW_WIDTH};
+use std::collections::BTreeSet;
+use std::io::{BufRead, Cursor};
+
+fn load(log: &str, load_options: LoadOptions) -> Data {
+    let mut data = Data {
+        load_options,
+        ..Data::new()
+    };
+    let reader: Box<dyn BufRead> = Box::new(Cursor::new(log.as_bytes().to_vec()));
+    data.load_reader(reader).unwrap();
+    data
+}
+
+#[test]
+fn each_talker_is_its_own_wind_series() {
+    let log = log(60, |_| {
+        vec![
+            "VWVHW,,T,,M,5.0,N,9.3,K,".to_string(),
+            "IIMWV,45.0,T,8.0,N,A".to_string(),
+            "WIMWV,50.0,T,14.0,N,A".to_string(),
+        ]
+    });
+    let data = load(
+        &log,
+        LoadOptions {
+            merge_sources: true,
+            ..LoadOptions::default()
+        },
+    );
+    let sources: BTreeSet<Option<&str>> = data.data.iter().map(|a| a.source.as_deref()).collect();
+    assert_eq!(sources, BTreeSet::from([Some("II"), Some("WI")]));
+    // Every second is there, the last one too.
+    assert_eq!(data.data.len(), 2 * 60);
+    for (source, windspeed) in [("II", 8.), ("WI", 14.)] {
+        assert!(data
+            .data
+            .iter()
+            .filter(|a| a.source.as_deref() == Some(source))
+            .all(|a| a.windspeed == Some(windspeed)));
+    }
+
+    // Drawn as two traces, the second a shade darker.
+    let pixels = data.graph(
+        200,
+        120,
+        start(),
+        start() + TimeDelta::seconds(60),
+        &GraphOptions {
+            legend: LegendPosition::Hidden,
+            ..GraphOptions::default()
+        },
+    );
+    let colour = Channel::WindSpeed.colour();
+    let darker = |a: u8| (a as f32 * 0.75) as u8;
+    let shaded = Rgb8Pixel {
+        r: darker(colour.r),
+        g: darker(colour.g),
+        b: darker(colour.b),
+    };
+    assert!(pixels.column_contains(100, colour));
+    assert!(pixels.column_contains(100, shaded));
+}
+
+#[test]
+fn merged_wind_is_kept_without_a_boat_speed() {
+    let log = log(10, |_| vec!["WIMWV,45.0,T,12.0,N,A".to_string()]);
+    let data = load(
+        &log,
+        LoadOptions {
+            merge_sources: true,
+            ..LoadOptions::default()
+        },
+    );
+    let times: Vec<i64> = data
+        .data
+        .iter()
+        .map(|a| (a.timestamp - start()).num_seconds())
+        .collect();
+    assert_eq!(times, (0..10).collect::<Vec<i64>>());
+    assert!(data
+        .data
+        .iter()
+        .all(|a| a.boatspeed.is_none() && a.windspeed == Some(12.)));
+}
+
+#[test]
+fn rmb_gives_the_waypoint_bearing_and_range() {
+    let log = log(10, |_| {
+        vec![
+            "VWVHW,,T,,M,5.0,N,9.3,K,".to_string(),
+            "WIMWV,45.0,T,12.0,N,A".to_string(),
+            "GPRMB,A,0.66,L,003,004,4917.24,N,12309.57,W,001.3,052.5,000.5,V".to_string(),
+        ]
+    });
+    let data = load(&log, LoadOptions::default());
+    assert!(!data.data.is_empty());
+    for point in &data.data[1..] {
+        assert_eq!(point.waypoint_bearing, Some(52.5));
+        assert_eq!(point.waypoint_range, Some(1.3));
+    }
+}
+
+#[test]
+fn bearings_are_wrapped_into_a_circle() {
+    let log = log(10, |_| {
+        vec![
+            "VWVHW,,T,,M,5.0,N,9.3,K,".to_string(),
+            "WIMWV,45.0,T,12.0,N,A".to_string(),
+            "HEHDT,370.0,T".to_string(),
+            "GPVTG,365.0,T,,M,6.0,N,11.1,K,A".to_string(),
+            "GPRMB,A,0.66,L,003,004,4917.24,N,12309.57,W,001.3,400.0,000.5,V".to_string(),
+        ]
+    });
+    let data = load(&log, LoadOptions::default());
+    assert!(!data.data.is_empty());
+    for point in &data.data[1..] {
+        assert_eq!(point.heading, Some(10.));
+        assert_eq!(point.cog, Some(5.));
+        assert_eq!(point.waypoint_bearing, Some(40.));
+    }
+}
+
+#[test]
+fn overview_has_a_column_per_pixel() {
+    let log = log(3600, |_| {
+        vec![
+            "VWVHW,,T,,M,5.0,N,9.3,K,".to_string(),
+            "WIMWV,45.0,T,12.0,N,A".to_string(),
+        ]
+    });
+    let data = load(&log, LoadOptions::default());
+    let columns = &data.overview.columns;
+    assert_eq!(columns.len(), OVERVIEW_WIDTH as usize);
+    // An hour of datapoints every second covers every column.
+    assert!(columns.iter().all(|a| a.is_some()));
+    let image = data.overview.image(OVERVIEW_HEIGHT);
+    assert_eq!(
+        (image.width, image.height),
+        (OVERVIEW_WIDTH, OVERVIEW_HEIGHT)
+    );
+
+    // Or added to as datapoints arrive, with the strip stretched to take them in.
+    let mut overview = Overview::new(&data.data[..600], OVERVIEW_WIDTH);
+    for from in (600..data.data.len()).step_by(60) {
+        overview.extend(&data.data[..(from + 60).min(data.data.len())], from);
+    }
+    assert!(overview.columns.iter().all(|a| a.is_some()));
+
+    // Even with nothing to show.
+    let empty = Data::from_datapoints(Vec::new());
+    assert_eq!(empty.overview.columns.len(), OVERVIEW_WIDTH as usize);
+}
+
+#[test]
+fn zero_is_missing_only_for_the_instrument_flagged() {
+    // Ten seconds each of both instruments reading, the boat stopped, the wind dropping to
+    // nothing, and both at zero.
+    let log = log(40, |second| {
+        let (boatspeed, windspeed) =
+            [(5., 10.), (0., 10.), (5., 0.), (0., 0.)][second as usize / 10];
+        vec![
+            format!("VWVHW,,T,,M,{boatspeed:.1},N,{:.1},K,", boatspeed * 1.852),
+            format!("WIMWV,45.0,T,{windspeed:.1},N,A"),
+        ]
+    });
+    for flags in [(false, false), (true, false), (false, true), (true, true)] {
+        let (wind_zero_missing, boat_zero_missing) = flags;
+        let data = load(
+            &log,
+            LoadOptions {
+                merge_sources: true,
+                wind_zero_missing,
+                boat_zero_missing,
+                ..LoadOptions::default()
+            },
+        );
+        let has = |boatspeed: f32, windspeed: f32| {
+            data.data
+                .iter()
+                .any(|a| a.boatspeed == Some(boatspeed) && a.windspeed == Some(windspeed))
+        };
+        assert!(has(5., 10.), "{flags:?}");
+        assert_eq!(has(0., 10.), !boat_zero_missing, "{flags:?}");
+        assert_eq!(has(5., 0.), !wind_zero_missing, "{flags:?}");
+        assert_eq!(
+            has(0., 0.),
+            !wind_zero_missing && !boat_zero_missing,
+            "{flags:?}"
+        );
+    }
+
+    // Without merging, a second with nothing but a zero boat speed is only a datapoint if zero
+    // is a reading.
+    let log = log(10, |_| vec!["VWVHW,,T,,M,0.0,N,0.0,K,".to_string()]);
+    for boat_zero_missing in [false, true] {
+        let data = load(
+            &log,
+            LoadOptions {
+                boat_zero_missing,
+                ..LoadOptions::default()
+            },
+        );
+        assert_eq!(data.data.is_empty(), boat_zero_missing);
+    }
+}
+
+#[test]
+fn true_wind_direction_adds_the_heading() {
+    let point = |heading, winddirection| DataPoint {
+        heading: Some(heading),
+        winddirection: Some(winddirection),
+        ..DataPoint::new()
+    };
+    assert_eq!(point(90., 30.).true_wind_direction(), Some(120.));
+    assert_eq!(point(350., 30.).true_wind_direction(), Some(20.));
+    assert_eq!(point(90., 330.).true_wind_direction(), Some(60.));
+
+    let log = log(10, |_| {
+        vec![
+            "HEHDT,90.0,T".to_string(),
+            "VWVHW,90.0,T,,M,5.0,N,9.3,K,".to_string(),
+            "WIMWV,30.0,T,12.0,N,A".to_string(),
+        ]
+    });
+    let data = load(&log, LoadOptions::default());
+    let directions = data.true_wind_direction();
+    assert!(!directions.is_empty());
+    assert!(directions.iter().all(|a| a.1 == 120.), "{directions:?}");
+
+    // Only drawn when asked for.
+    for channels in [Channel::DEFAULT.to_vec(), vec![Channel::TrueWindDirection]] {
+        let pixels = data.graph(
+            200,
+            120,
+            start(),
+            start() + TimeDelta::seconds(10),
+            &GraphOptions {
+                channels: channels.clone(),
+                legend: LegendPosition::Hidden,
+                ..GraphOptions::default()
+            },
+        );
+        assert_eq!(
+            pixels.column_contains(100, Channel::TrueWindDirection.colour()),
+            channels.contains(&Channel::TrueWindDirection)
+        );
+    }
+}
+
+#[test]
+fn wind_angles_are_wrapped_into_0_to_360() {
+    let angles = [-5., 365., 45., 360.];
+    let log = log(4, |second| {
+        vec![
+            "VWVHW,,T,,M,5.0,N,9.3,K,".to_string(),
+            format!("WIMWV,{:.1},T,12.0,N,A", angles[second as usize]),
+        ]
+    });
+    let data = load(&log, LoadOptions::default());
+    let directions: Vec<Option<f32>> = data.data.iter().map(|a| a.winddirection).collect();
+    assert_eq!(directions, vec![Some(355.), Some(5.), Some(45.), Some(0.)]);
+    assert_eq!(data.normalized_angles, 3);
+}
+
+#[test]
+fn profile_times_each_loading_stage() {
+    let log = log(10, |_| {
+        vec![
+            "VWVHW,,T,,M,5.0,N,9.3,K,".to_string(),
+            "WIMWV,45.0,T,12.0,N,A".to_string(),
+        ]
+    });
+    let data = load(&log, LoadOptions::default());
+    let stages: Vec<&str> = data.profile.stages.iter().map(|a| a.0.as_str()).collect();
+    assert_eq!(stages, ["read", "parse", "assemble", "overview"]);
+}
+
+#[test]
+fn sentences_read_but_not_used_are_counted() {
+    // XTE is parsed, but nothing in it is graphed.
+    let log = log(10, |_| {
+        vec![
+            "VWVHW,,T,,M,5.0,N,9.3,K,".to_string(),
+            "WIMWV,45.0,T,12.0,N,A".to_string(),
+            "GPXTE,A,A,0.010,L,N,A".to_string(),
+        ]
+    });
+    let data = load(&log, LoadOptions::default());
+    assert!(!data.data.is_empty());
+    assert_eq!(
+        data.unhandled_sentences.into_iter().collect::<Vec<_>>(),
+        [("XTE".to_string(), 10)]
+    );
+}
@@ -0,0 +1,69 @@
+use clap::Parser;
+use make_polar_rs::channels::Channel;
+use make_polar_rs::options::{
+    Options, GRAPH_IMAGE_HEIGHT, GRAPH_IMAGE_WIDTH, MAXIMUM_GRAPH_SIZE, MINIMUM_GRAPH_SIZE,
+};
+
+#[derive(Parser)]
+struct Cli {
+    #[command(flatten)]
+    options: Options,
+}
+
+fn parse(args: &[&str]) -> Options {
+    Cli::parse_from(std::iter::once("make-polar-rs").chain(args.iter().copied())).options
+}
+
+// Loads `text` as a config file named for `test`, so that tests running at the same time don't
+// share one.
+fn config(test: &str, text: &str) -> Options {
+    let path = std::env::temp_dir().join(format!("make-polar-{test}-{}.toml", std::process::id()));
+    std::fs::write(&path, text).unwrap();
+    let options = Options::load_config(Some(&path));
+    let _ = std::fs::remove_file(&path);
+    options
+}
+
+#[test]
+fn config_file_applies_unless_the_command_line_says_otherwise() {
+    let file = config(
+        "overrides",
+        "scale-percentile = 95.0\ndaynight = true\nchannels = [\"heading\"]\nmerge-sources = true\n",
+    );
+    let options = parse(&["--scale-percentile", "99", "--daynight=false"]).or(file);
+    let graph_options = options.graph_options();
+    assert_eq!(graph_options.scale_percentile, Some(99.));
+    assert!(!graph_options.daynight);
+    assert_eq!(graph_options.channels, [Channel::Heading]);
+    assert!(options.load_options().merge_sources);
+}
+
+#[test]
+fn defaults_apply_when_neither_sets_a_value() {
+    let options = parse(&[]).or(config("defaults", "wind-zero-missing = true\n"));
+    let graph_options = options.graph_options();
+    assert_eq!(graph_options.scale_percentile, None);
+    assert_eq!(graph_options.channels, Channel::DEFAULT);
+    let load_options = options.load_options();
+    assert!(load_options.wind_zero_missing);
+    assert!(!load_options.boat_zero_missing);
+}
+
+#[test]
+fn config_file_sets_the_graph_size_unless_the_command_line_does() {
+    let file = config("size", "width = 1500\nheight = 600\n");
+    let options = parse(&["--width", "800"]).or(file);
+    assert_eq!((options.width(), options.height()), (800, 600));
+    let options = parse(&[]).or(config("no-size", ""));
+    assert_eq!(
+        (options.width(), options.height()),
+        (GRAPH_IMAGE_WIDTH, GRAPH_IMAGE_HEIGHT)
+    );
+}
+
+#[test]
+fn config_file_sizes_are_kept_to_the_command_line_limits() {
+    let options = parse(&[]).or(config("limits", "width = 10\nheight = 1000000\n"));
+    assert_eq!(options.width(), MINIMUM_GRAPH_SIZE as u32);
+    assert_eq!(options.height(), MAXIMUM_GRAPH_SIZE as u32);
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use common::sweep;
+use make_polar_rs::polar::{PointOfSail, SailingModes, POLAR_TWA_STEP};
+use std::path::{Path, PathBuf};
+
+// The true wind angles of the rows of a polar table with any boat speed in them.
+fn angles_with_data(csv: &str) -> Vec<f32> {
+    csv.lines()
+        .skip(1)
+        .filter_map(|row| {
+            let mut cells = row.split(',');
+            let angle: f32 = cells.next()?.parse().ok()?;
+            cells
+                .any(|a| a.parse::<f32>().is_ok_and(|b| b > 0.))
+                .then_some(angle)
+        })
+        .collect()
+}
+
+#[test]
+fn angles_are_classified_into_points_of_sail() {
+    let modes = SailingModes::default();
+    assert_eq!(modes.classify(30.), PointOfSail::Upwind);
+    assert_eq!(modes.classify(330.), PointOfSail::Upwind);
+    assert_eq!(modes.classify(90.), PointOfSail::Reaching);
+    assert_eq!(modes.classify(150.), PointOfSail::Downwind);
+    assert_eq!(modes.classify(-150.), PointOfSail::Downwind);
+    let modes = SailingModes {
+        upwind_max: 50.,
+        downwind_min: 80.,
+    };
+    assert_eq!(modes.classify(60.), PointOfSail::Reaching);
+    assert_eq!(modes.classify(90.), PointOfSail::Downwind);
+}
+
+#[test]
+fn each_point_of_sail_has_its_own_table() {
+    let data = sweep(6.);
+    let tables: Vec<(PointOfSail, Vec<f32>)> = data
+        .by_point_of_sail(&SailingModes::default())
+        .into_iter()
+        .map(|(point_of_sail, points)| {
+            let csv = points.export_polar_csv(&[10.], POLAR_TWA_STEP);
+            (point_of_sail, angles_with_data(&csv))
+        })
+        .collect();
+    let step = |low: i32, high: i32| (low..=high).map(|a| a as f32 * 5.).collect::<Vec<f32>>();
+    assert_eq!(
+        tables,
+        vec![
+            (PointOfSail::Upwind, step(6, 13)),
+            (PointOfSail::Reaching, step(14, 24)),
+            (PointOfSail::Downwind, step(25, 36)),
+        ]
+    );
+}
+
+#[test]
+fn point_of_sail_is_added_to_file_names() {
+    assert_eq!(
+        PointOfSail::Upwind.file_name(Path::new("out/polar.csv")),
+        PathBuf::from("out/polar-upwind.csv")
+    );
+    assert_eq!(
+        PointOfSail::Downwind.file_name(Path::new("polar")),
+        PathBuf::from("polar-downwind")
+    );
+}
+
+#[test]
+fn comparison_curves_are_drawn_over_the_polar() {
+    let tws_bands = [10.];
+    let data = sweep(6.);
+    let polar = data.polar(400, 400, &tws_bands);
+    let compared = data.polar_with_comparison(Some(&sweep(3.)), 400, 400, &tws_bands);
+    assert_ne!(polar, compared);
+    assert_eq!(
+        polar,
+        data.polar_with_comparison(None, 400, 400, &tws_bands)
+    );
+}
@@ -0,0 +1,166 @@
+mod common;
+
+use chrono::TimeDelta;
+use common::{point, start, steady};
+use make_polar_rs::datapoints::{Data, DataPoint};
+use make_polar_rs::export::Pixels;
+use make_polar_rs::graph::GraphOptions;
+use make_polar_rs::view::{
+    is_following, live_offsets, padded_range, Datasets, GraphCache, ViewState,
+    MINIMUM_TIME_SPAN_SECONDS,
+};
+use std::cell::Cell;
+
+const WIDTH: u32 = 200;
+const HEIGHT: u32 = 120;
+const DESPIKE_THRESHOLD: f32 = 5.;
+
+fn view() -> ViewState {
+    ViewState {
+        start: start(),
+        end: start() + TimeDelta::seconds(60),
+        options: GraphOptions::default(),
+        despike: false,
+    }
+}
+
+#[test]
+fn following_pauses_away_from_the_end_and_resumes_at_it() {
+    let mut max = 100.;
+    let (mut start, mut end) = (40., 100.);
+    assert!(is_following(end, max));
+
+    // Scrolled back to look at something.
+    (start, end) = (20., 80.);
+    assert!(!is_following(end, max));
+    // Data arriving doesn't move a paused view, and it stays paused.
+    max = 110.;
+    assert!(!is_following(end, max));
+
+    // The Live button keeps the span and jumps to the end.
+    (start, end) = live_offsets(start, end, max);
+    assert_eq!((start, end), (50., 110.));
+    assert!(is_following(end, max));
+
+    // Scrolling back to the end by hand resumes following too.
+    end = 90.;
+    assert!(!is_following(end, max));
+    end = max;
+    assert!(is_following(end, max));
+    assert_eq!(start, 50.);
+
+    // A span longer than the data starts at its beginning.
+    assert_eq!(live_offsets(0., 100., 50.), (0., 50.));
+}
+
+#[test]
+fn one_timestamp_still_gives_the_scrollers_a_range() {
+    let (min, max) = padded_range((start(), start()));
+    assert_eq!(min, start());
+    assert_eq!(max - min, TimeDelta::seconds(MINIMUM_TIME_SPAN_SECONDS));
+    assert!((max - min).num_seconds() > 0);
+
+    // Anything longer is left as it is.
+    let end = start() + TimeDelta::seconds(59);
+    assert_eq!(padded_range((start(), end)), (start(), end));
+}
+
+#[test]
+fn export_is_drawn_from_the_current_view() {
+    let data = steady(60, 5.);
+    let datasets = Datasets::new(data.clone(), DESPIKE_THRESHOLD, None);
+    let mut view = view();
+    assert_eq!(
+        view.export(&datasets, WIDTH, HEIGHT, 1),
+        view.render(&datasets, WIDTH, HEIGHT)
+    );
+
+    let exported = view.export(&datasets, WIDTH, HEIGHT, 2);
+    assert_eq!((exported.width, exported.height), (2 * WIDTH, 2 * HEIGHT));
+    assert_eq!(
+        exported,
+        data.graph(2 * WIDTH, 2 * HEIGHT, view.start, view.end, &view.options)
+    );
+
+    // Changes to the view carry over to the next export.
+    view.start += TimeDelta::seconds(10);
+    view.options.scale_percentile = Some(50.);
+    let changed = view.export(&datasets, WIDTH, HEIGHT, 2);
+    assert_eq!(
+        changed,
+        data.graph(2 * WIDTH, 2 * HEIGHT, view.start, view.end, &view.options)
+    );
+    assert_ne!(changed, exported);
+}
+
+#[test]
+fn cache_redraws_whenever_the_data_changes() {
+    let mut datasets = Datasets::new(steady(60, 5.), DESPIKE_THRESHOLD, None);
+    let view = view();
+    let mut cache = GraphCache::default();
+    let draws = Cell::new(0);
+    let mut render = |datasets: &Datasets| -> Pixels {
+        cache.render(&view, datasets, WIDTH, HEIGHT, |a| {
+            draws.set(draws.get() + 1);
+            a
+        })
+    };
+
+    render(&datasets);
+    render(&datasets);
+    assert_eq!(draws.get(), 1);
+
+    // None of these change the number of datapoints.
+    datasets.raw_changed(DESPIKE_THRESHOLD);
+    render(&datasets);
+    assert_eq!(draws.get(), 2);
+    datasets.set_comparison(Some(steady(60, 3.)));
+    render(&datasets);
+    assert_eq!(draws.get(), 3);
+    datasets.replace_raw(steady(60, 6.), DESPIKE_THRESHOLD);
+    let pixels = render(&datasets);
+    assert_eq!(draws.get(), 4);
+    assert_eq!(pixels, view.render(&datasets, WIDTH, HEIGHT));
+}
+
+#[test]
+fn appended_datapoints_are_despiked_and_counted_as_if_loaded_together() {
+    // Tacking every minute, with a spike either side of where the data is split.
+    let mut points: Vec<DataPoint> = (0..600)
+        .map(|a| DataPoint {
+            winddirection: Some(if (a / 60) % 2 == 0 { 45. } else { 315. }),
+            ..point(a, 5.)
+        })
+        .collect();
+    points[299].boatspeed = Some(30.);
+    points[300].windspeed = Some(40.);
+    let whole = Datasets::new(
+        Data::from_datapoints(points.clone()),
+        DESPIKE_THRESHOLD,
+        None,
+    );
+    let mut datasets = Datasets::new(
+        Data::from_datapoints(points[..300].to_vec()),
+        DESPIKE_THRESHOLD,
+        None,
+    );
+    for batch in points[300..].chunks(7) {
+        datasets.raw.data.extend_from_slice(batch);
+        datasets.raw_changed(DESPIKE_THRESHOLD);
+    }
+
+    let readings = |data: &Data| -> Vec<(Option<f32>, Option<f32>)> {
+        data.data
+            .iter()
+            .map(|a| (a.boatspeed, a.windspeed))
+            .collect()
+    };
+    assert_eq!(readings(&datasets.despiked), readings(&whole.despiked));
+    assert_eq!(datasets.despiked.spikes.len(), 2);
+    assert_eq!(whole.despiked.spikes.len(), 2);
+    let totals = &datasets.totals;
+    assert_eq!((totals.tacks, totals.gybes), (9, 0));
+    assert_eq!((whole.totals.tacks, whole.totals.gybes), (9, 0));
+    assert!((totals.distance_sailed - whole.totals.distance_sailed).abs() < 1e-9);
+    assert!((totals.distance_sailed - datasets.raw.distance_sailed()).abs() < 1e-9);
+}